@@ -1,152 +1,282 @@
+use crate::constants::MAX_ACTIVITY_SPEED_PERCENT;
+use crate::equipment::Equipment;
 use crate::inventory::Inventory;
 use crate::item::Item;
 use crate::job::Job;
-use crate::job::JobName;
+use crate::loot::LootTable;
+use crate::rng::Rng;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
-pub enum ActivityName {
-    Woodcutting,
-    Mining,
-    Farming,
+// Durations are in milliseconds so that progress is exact however it is split up
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ActivityData {
+    pub id: u128,
+    pub name: String,
+    pub description: String,
+    pub duration_ms: u64,
+    pub experience: Vec<(u128, u128)>,
+    pub items: Vec<Item>,
+    // rolled on top of the items every cycle
+    pub loot: Option<LootTable>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Activity {
-    pub name: ActivityName,
+    pub id: u128,
+    pub name: String,
     pub description: String,
-    pub duration: f32,
-    pub timer: f32,
-    pub experience: Vec<(JobName, u128)>,
+    pub duration_ms: u64,
+    pub timer_ms: u64,
+    pub experience: Vec<(u128, u128)>,
     pub items: Vec<Item>,
+    // the bonus item chance builds up over the cycles instead of being rolled, so progress
+    // does not depend on how it is split up. In percent of a bonus.
+    pub bonus_progress: u32,
+    pub loot: Option<LootTable>,
 }
 
 impl Activity {
     pub fn new(
-        name: ActivityName,
+        id: u128,
+        name: String,
         description: String,
-        duration: f32,
-        experience: Vec<(JobName, u128)>,
+        duration_ms: u64,
+        experience: Vec<(u128, u128)>,
         items: Vec<Item>,
     ) -> Self {
         Self {
+            id,
             name,
             description,
-            duration,
+            duration_ms,
             experience,
-            timer: 0.0,
+            timer_ms: 0,
             items,
+            bonus_progress: 0,
+            loot: None,
         }
     }
 
+    pub fn from_data(activity_data: &ActivityData) -> Self {
+        let mut activity = Self::new(
+            activity_data.id,
+            activity_data.name.clone(),
+            activity_data.description.clone(),
+            activity_data.duration_ms,
+            activity_data.experience.clone(),
+            activity_data.items.clone(),
+        );
+        activity.loot = activity_data.loot.clone();
+        activity
+    }
+
+    // The activity keeps its own copy of the data, except for the loot table, which saves from
+    // before loot did not have
+    pub fn update_data(&mut self, activity_data: &ActivityData) {
+        self.loot = activity_data.loot.clone();
+    }
+
+    // Rewards every cycle completed in the elapsed time at once, so a long absence costs
+    // the same as a single frame. Returns the number of cycles completed.
     pub fn update(
         &mut self,
-        delta_time: f32,
-        jobs: &mut Vec<Job>,
+        elapsed_ms: u64,
+        jobs: &mut [Job],
+        equipment: &[Equipment],
         inventory: &mut Inventory,
-    ) -> Result<(), String> {
-        self.timer += delta_time;
-        if self.timer >= self.duration {
-            self.timer = 0.0;
-            self.reward(jobs, inventory)?;
+        rng: &mut Rng,
+    ) -> Result<u128, String> {
+        let total_ms = u128::from(self.timer_ms) + u128::from(elapsed_ms);
+        let duration_ms = u128::from(self.cycle_ms(jobs, equipment));
+        let cycles = total_ms / duration_ms;
+        self.timer_ms = (total_ms % duration_ms) as u64;
+
+        if cycles > 0 {
+            self.reward(cycles, jobs, equipment, inventory, rng)?;
         }
-        Ok(())
+        Ok(cycles)
+    }
+
+    // The length of a cycle once the perks of the jobs it trains and the equipment are applied
+    pub fn cycle_ms(&self, jobs: &[Job], equipment: &[Equipment]) -> u64 {
+        let percent: u32 = self.trained_jobs(jobs).map(Job::faster_activity_percent).sum::<u32>()
+            + self.equipment_percent(equipment, Equipment::faster_activity_percent);
+        let percent = u64::from(percent.min(MAX_ACTIVITY_SPEED_PERCENT));
+        (self.duration_ms * (100 - percent) / 100).max(1)
+    }
+
+    // Between 0 and 1, how far the current cycle is
+    pub fn progress(&self, jobs: &[Job], equipment: &[Equipment]) -> f32 {
+        self.timer_ms as f32 / self.cycle_ms(jobs, equipment) as f32
+    }
+
+    // The number of cycles until a job this activity trains levels up, None if none of them will.
+    // A level up can unlock perks and loot, so the cycles before it can be rewarded at once.
+    pub fn cycles_until_level_up(&self, jobs: &[Job]) -> Option<u128> {
+        self.experience
+            .iter()
+            .filter_map(|(job_id, _)| {
+                let job = jobs.iter().find(|job| job.id == *job_id)?;
+                let gain = self.experience_per_cycle(job);
+                if gain == 0 {
+                    return None;
+                }
+                Some(job.get_xp_needed_for_next_level()?.div_ceil(gain).max(1))
+            })
+            .min()
+    }
+
+    // Rounded per cycle, so the experience does not depend on how the cycles are batched
+    fn experience_per_cycle(&self, job: &Job) -> u128 {
+        let percent = u128::from(job.extra_experience_percent());
+        self.experience
+            .iter()
+            .filter(|(id, _)| *id == job.id)
+            .map(|(_, experience)| experience * (100 + percent) / 100)
+            .sum()
     }
 
-    pub fn update_from_time_elapsed(
+    fn trained_jobs<'a>(&'a self, jobs: &'a [Job]) -> impl Iterator<Item = &'a Job> {
+        jobs.iter().filter(|job| self.experience.iter().any(|(id, _)| *id == job.id))
+    }
+
+    // An effect of the equipment added up over the jobs this activity trains
+    fn equipment_percent(&self, equipment: &[Equipment], effect: fn(&Equipment, u128) -> u32) -> u32 {
+        equipment
+            .iter()
+            .flat_map(|equipment| self.experience.iter().map(move |(job, _)| effect(equipment, *job)))
+            .sum()
+    }
+
+    fn reward(
         &mut self,
-        time_elapsed: u64,
-        jobs: &mut Vec<Job>,
+        cycles: u128,
+        jobs: &mut [Job],
+        equipment: &[Equipment],
         inventory: &mut Inventory,
+        rng: &mut Rng,
     ) -> Result<(), String> {
-        let number_of_updates: u32 = ((time_elapsed as f32 + self.timer) / self.duration) as u32;
-        for _ in 0..number_of_updates {
-            self.reward(jobs, inventory)?;
+        // perks and loot entries unlocked by this reward only count from the next cycle
+        let bonus_percent: u32 = self.trained_jobs(jobs).map(Job::bonus_item_percent).sum::<u32>()
+            + self.equipment_percent(equipment, Equipment::extra_yield_percent);
+        let loot = match &self.loot {
+            Some(loot) => loot.roll(cycles, jobs, rng),
+            None => vec![],
+        };
+        self.reward_experience(cycles, jobs)?;
+        self.reward_items(cycles, bonus_percent, inventory);
+        // what does not fit under the stack limits is lost
+        for item in &loot {
+            let _ = inventory.add_item(item);
         }
-
-        self.timer = (time_elapsed as f32 + self.timer) % self.duration;
         Ok(())
     }
 
-    fn reward(&mut self, jobs: &mut Vec<Job>, inventory: &mut Inventory) -> Result<(), String> {
-        self.reward_experience(jobs)?;
-        self.reward_items(inventory);
-        Ok(())
-    }
-
-    fn reward_experience(&mut self, jobs: &mut Vec<Job>) -> Result<(), String> {
+    fn reward_experience(&mut self, cycles: u128, jobs: &mut [Job]) -> Result<(), String> {
         for (job, experience) in &self.experience {
-            match jobs.iter_mut().find(|j| j.name == *job) {
-                Some(job) => job.add_experience(*experience),
+            match jobs.iter_mut().find(|j| j.id == *job) {
+                Some(job) => {
+                    let percent = u128::from(job.extra_experience_percent());
+                    job.add_experience(experience * (100 + percent) / 100 * cycles)
+                }
                 None => return Err(format!("Job not found: {}", job)),
             }
         }
         Ok(())
     }
 
-    fn reward_items(&mut self, inventory: &mut Inventory) {
+    fn reward_items(&mut self, cycles: u128, bonus_percent: u32, inventory: &mut Inventory) {
+        let bonus_progress = u128::from(self.bonus_progress) + u128::from(bonus_percent) * cycles;
+        let bonuses = bonus_progress / 100;
+        self.bonus_progress = (bonus_progress % 100) as u32;
         for item in &self.items {
-            inventory.add_item(item);
+            let _ = inventory.add_item(&item.scaled(cycles + bonuses));
         }
     }
 }
 
 impl fmt::Display for Activity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.name {
-            ActivityName::Woodcutting => write!(f, "Woodcutting"),
-            ActivityName::Mining => write!(f, "Mining"),
-            ActivityName::Farming => write!(f, "Farming"),
-        }
+        write!(f, "{}", self.name)
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
-    use crate::constants::LEVEL_UP_EXPERIENCE;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+    use crate::xp_curve::{XpCurve, XpFormula};
+    use crate::job::{Perk, PerkEffect};
 
     #[test]
     fn test_activity_new() {
         let activity = Activity::new(
-            ActivityName::Woodcutting,
+            1,
+            "Woodcutting".to_string(),
             "Cutting down trees".to_string(),
-            1000.0,
-            vec![(JobName::Woodcutter, 100)],
+            1000,
+            vec![(WOODCUTTER, 100)],
             vec![Item::new(1, 1)],
         );
-        assert_eq!(activity.name, ActivityName::Woodcutting);
+        assert_eq!(activity.id, 1);
+        assert_eq!(activity.name, "Woodcutting");
         assert_eq!(activity.description, "Cutting down trees");
-        assert_eq!(activity.duration, 1000.0);
-        assert_eq!(activity.experience, vec![(JobName::Woodcutter, 100)]);
+        assert_eq!(activity.duration_ms, 1000);
+        assert_eq!(activity.experience, vec![(WOODCUTTER, 100)]);
         assert_eq!(
             activity.items,
             vec![Item::new(1, 1)]
         );
     }
 
+    #[test]
+    fn test_activity_from_data() {
+        let activity_data = ActivityData {
+            id: 2,
+            name: "Mining".to_string(),
+            description: "Mining rocks".to_string(),
+            duration_ms: 10000,
+            experience: vec![(MINER, 100)],
+            items: vec![Item::new(2, 1)],
+            loot: None,
+        };
+        let activity = Activity::from_data(&activity_data);
+        assert_eq!(activity.id, activity_data.id);
+        assert_eq!(activity.name, activity_data.name);
+        assert_eq!(activity.description, activity_data.description);
+        assert_eq!(activity.duration_ms, activity_data.duration_ms);
+        assert_eq!(activity.timer_ms, 0);
+        assert_eq!(activity.experience, activity_data.experience);
+        assert_eq!(activity.items, activity_data.items);
+    }
+
     #[test]
     fn test_activity_update() {
         let mut jobs = vec![Job::new(
-            JobName::Woodcutter,
+            WOODCUTTER,
             "Woodcutter".to_string(),
+            String::new(),
+            String::new(),
             0,
             1,
-            LEVEL_UP_EXPERIENCE.to_vec(),
+            standard_curve(),
         )];
         let mut inventory = Inventory::new();
         let mut activity = Activity::new(
-            ActivityName::Woodcutting,
+            1,
+            "Woodcutting".to_string(),
             "Cutting down trees".to_string(),
-            1000.0,
-            vec![(JobName::Woodcutter, LEVEL_UP_EXPERIENCE[0] / 2)],
+            1000,
+            vec![(WOODCUTTER, level_up_experience(1) / 2)],
             vec![Item::new(1, 1)],
         );
-        activity.update(500.0, &mut jobs, &mut inventory).unwrap();
-        assert_eq!(activity.timer, 500.0);
+        activity.update(500, &mut jobs, &[], &mut inventory, &mut Rng::new(1)).unwrap();
+        assert_eq!(activity.timer_ms, 500);
         assert_eq!(
             activity.experience,
-            vec![(JobName::Woodcutter, LEVEL_UP_EXPERIENCE[0] / 2)]
+            vec![(WOODCUTTER, level_up_experience(1) / 2)]
         );
         assert_eq!(inventory.items.len(), 0);
         assert_eq!(jobs[0].experience, 0);
@@ -156,30 +286,33 @@ mod tests {
     #[test]
     fn test_activity_complete() {
         let mut jobs = vec![Job::new(
-            JobName::Woodcutter,
+            WOODCUTTER,
             "Woodcutter".to_string(),
+            String::new(),
+            String::new(),
             0,
             1,
-            LEVEL_UP_EXPERIENCE.to_vec(),
+            standard_curve(),
         )];
         let mut inventory = Inventory::new();
         let item = Item::new(1, 1);
         let mut activity = Activity::new(
-            ActivityName::Woodcutting,
+            1,
+            "Woodcutting".to_string(),
             "Cutting down trees".to_string(),
-            1000.0,
-            vec![(JobName::Woodcutter, LEVEL_UP_EXPERIENCE[0] / 2)],
+            1000,
+            vec![(WOODCUTTER, level_up_experience(1) / 2)],
             vec![item.clone()],
         );
-        activity.update(1000.0, &mut jobs, &mut inventory).unwrap();
-        assert_eq!(activity.timer, 0.0);
+        activity.update(1000, &mut jobs, &[], &mut inventory, &mut Rng::new(1)).unwrap();
+        assert_eq!(activity.timer_ms, 0);
         assert_eq!(
             activity.experience,
-            vec![(JobName::Woodcutter, LEVEL_UP_EXPERIENCE[0] / 2)]
+            vec![(WOODCUTTER, level_up_experience(1) / 2)]
         );
         assert_eq!(inventory.items.len(), 1);
         assert_eq!(inventory.items[&item.id].quantity, 1);
-        assert_eq!(jobs[0].experience, LEVEL_UP_EXPERIENCE[0] / 2);
+        assert_eq!(jobs[0].experience, level_up_experience(1) / 2);
         assert_eq!(jobs[0].level, 1);
     }
 
@@ -187,25 +320,31 @@ mod tests {
     fn test_activity_update_jobs() {
         let mut jobs = vec![
             Job::new(
-                JobName::Woodcutter,
+                WOODCUTTER,
                 "Woodcutter".to_string(),
+                String::new(),
+                String::new(),
                 0,
                 1,
-                LEVEL_UP_EXPERIENCE.to_vec(),
+                standard_curve(),
             ),
             Job::new(
-                JobName::Miner,
+                MINER,
                 "Miner".to_string(),
+                String::new(),
+                String::new(),
                 0,
                 1,
-                LEVEL_UP_EXPERIENCE.to_vec(),
+                standard_curve(),
             ),
             Job::new(
-                JobName::Farmer,
+                FARMER,
                 "Farmer".to_string(),
+                String::new(),
+                String::new(),
                 0,
                 1,
-                LEVEL_UP_EXPERIENCE.to_vec(),
+                standard_curve(),
             ),
         ];
         let mut inventory = Inventory::new();
@@ -213,13 +352,14 @@ mod tests {
         let stone = Item::new(2, 2);
         let wheat = Item::new(3, 3);
         let mut activity = Activity::new(
-            ActivityName::Woodcutting,
+            1,
+            "Woodcutting".to_string(),
             "Cutting down trees".to_string(),
-            1000.0,
+            1000,
             vec![
-                (JobName::Woodcutter, LEVEL_UP_EXPERIENCE[0] / 2),
-                (JobName::Miner, LEVEL_UP_EXPERIENCE[0]),
-                (JobName::Farmer, LEVEL_UP_EXPERIENCE[0] * 2),
+                (WOODCUTTER, level_up_experience(1) / 2),
+                (MINER, level_up_experience(1)),
+                (FARMER, level_up_experience(1) * 2),
             ],
             vec![
                 wood.clone(),
@@ -228,12 +368,12 @@ mod tests {
             ],
         );
 
-        activity.update(1000.0, &mut jobs, &mut inventory).unwrap();
-        assert_eq!(jobs[0].experience, LEVEL_UP_EXPERIENCE[0] / 2);
+        activity.update(1000, &mut jobs, &[], &mut inventory, &mut Rng::new(1)).unwrap();
+        assert_eq!(jobs[0].experience, level_up_experience(1) / 2);
         assert_eq!(jobs[0].level, 1);
         assert_eq!(jobs[1].experience, 0);
         assert_eq!(jobs[1].level, 2);
-        assert_eq!(jobs[2].experience, LEVEL_UP_EXPERIENCE[0]);
+        assert_eq!(jobs[2].experience, level_up_experience(1));
         assert_eq!(jobs[2].level, 2);
         assert_eq!(inventory.items.len(), 3);
         assert_eq!(inventory.items[&wood.id].quantity, 1);
@@ -247,24 +387,32 @@ mod tests {
         // using some custom level up experience for testing
         let level_up_experience: Vec<u128> = (0..100).map(|i| 100 * 2u128.pow(i as u32)).collect();
         let mut jobs = vec![Job::new(
-            JobName::Woodcutter,
+            WOODCUTTER,
             "Woodcutter".to_string(),
+            String::new(),
+            String::new(),
             0,
             1,
-            level_up_experience.clone(),
+            XpCurve {
+                id: 2,
+                name: "Doubling".to_string(),
+                max_level: 100,
+                formula: XpFormula::Table(level_up_experience.clone()),
+            },
         )];
         let mut inventory = Inventory::new();
         let wood = Item::new(1, 1);
-        let activity_duration = 1000.0;
+        let activity_duration = 1_000_000;
         let activity_experience = level_up_experience[0];
         let mut activity = Activity::new(
-            ActivityName::Woodcutting,
+            1,
+            "Woodcutting".to_string(),
             "Cutting down trees".to_string(),
             activity_duration,
-            vec![(JobName::Woodcutter, activity_experience)],
+            vec![(WOODCUTTER, activity_experience)],
             vec![wood.clone()],
         );
-        activity.update(500.0, &mut jobs, &mut inventory).unwrap();
+        activity.update(500_000, &mut jobs, &[], &mut inventory, &mut Rng::new(1)).unwrap();
 
         // level 2 -> 100
         // level 3 -> 200
@@ -274,13 +422,107 @@ mod tests {
         // 10 cycles is 100 * 10 = 1000 xp
         // so we should level up to 4 and have 300 xp left
         activity
-            .update_from_time_elapsed(9700, &mut jobs, &mut inventory)
+            .update(9_700_000, &mut jobs, &[], &mut inventory, &mut Rng::new(1))
             .unwrap();
 
-        assert_eq!(activity.timer, 200.0);
+        assert_eq!(activity.timer_ms, 200_000);
         assert_eq!(inventory.items.len(), 1);
         assert_eq!(inventory.items[&wood.id].quantity, 10);
         assert_eq!(jobs[0].experience, 300);
         assert_eq!(jobs[0].level, 4);
     }
+
+    #[test]
+    fn test_activity_update_from_time_elapsed_after_a_month() {
+        let mut jobs = vec![Job::new(
+            MINER,
+            "Miner".to_string(),
+            String::new(),
+            String::new(),
+            0,
+            1,
+            standard_curve(),
+        )];
+        let mut inventory = Inventory::new();
+        let mut activity = Activity::new(
+            2,
+            "Mining".to_string(),
+            "Mining rocks".to_string(),
+            10_000,
+            vec![(MINER, 1)],
+            vec![Item::new(2, 3)],
+        );
+        activity.update(2_500, &mut jobs, &[], &mut inventory, &mut Rng::new(1)).unwrap();
+
+        // 30 days and 2.5 seconds is 259200 cycles and 2.5 seconds left over
+        activity
+            .update(30 * 24 * 60 * 60 * 1000, &mut jobs, &[], &mut inventory, &mut Rng::new(1))
+            .unwrap();
+
+        assert_eq!(activity.timer_ms, 2_500);
+        assert_eq!(inventory.get_item_quantity(2), 259_200 * 3);
+        assert_eq!(jobs[0].total_experience(), 259_200);
+    }
+
+    fn woodcutter_with_perks(level: u8) -> Job {
+        let mut job = Job::new(WOODCUTTER, "Woodcutter".to_string(), String::new(), String::new(), 0, level, standard_curve());
+        job.perks = vec![
+            Perk { level: 2, name: "Sharpened Axe".to_string(), effect: PerkEffect::FasterActivity(20) },
+            Perk { level: 3, name: "Keen Eye".to_string(), effect: PerkEffect::BonusItemChance(30) },
+            Perk { level: 4, name: "Insight".to_string(), effect: PerkEffect::ExtraExperience(50) },
+        ];
+        job
+    }
+
+    #[test]
+    fn test_activity_perks() {
+        let mut activity = Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cutting down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 10)],
+            vec![Item::new(1, 2)],
+        );
+        assert_eq!(activity.cycle_ms(&[woodcutter_with_perks(1)], &[]), 10_000);
+
+        let mut jobs = vec![woodcutter_with_perks(4)];
+        let mut inventory = Inventory::new();
+        assert_eq!(activity.cycle_ms(&jobs, &[]), 8_000);
+
+        let cycles = activity.update(80_000, &mut jobs, &[], &mut inventory, &mut Rng::new(1)).unwrap();
+
+        // 10 cycles with a 30% bonus chance are 3 bonuses and 0% towards the next one
+        assert_eq!(cycles, 10);
+        assert_eq!(inventory.get_item_quantity(1), 26);
+        assert_eq!(activity.bonus_progress, 0);
+        assert_eq!(jobs[0].experience, 150);
+    }
+
+    #[test]
+    fn test_activity_bonus_items_do_not_depend_on_batching() {
+        let activity = Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cutting down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 0)],
+            vec![Item::new(1, 1)],
+        );
+
+        let mut batched = activity.clone();
+        let mut batched_inventory = Inventory::new();
+        batched.update(56_000, &mut [woodcutter_with_perks(3)], &[], &mut batched_inventory, &mut Rng::new(1)).unwrap();
+
+        let mut single = activity.clone();
+        let mut single_inventory = Inventory::new();
+        for _ in 0..7 {
+            single.update(8_000, &mut [woodcutter_with_perks(3)], &[], &mut single_inventory, &mut Rng::new(1)).unwrap();
+        }
+
+        assert_eq!(batched_inventory.get_item_quantity(1), 9);
+        assert_eq!(batched.bonus_progress, 10);
+        assert_eq!(batched_inventory, single_inventory);
+        assert_eq!(batched, single);
+    }
 }
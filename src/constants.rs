@@ -1,106 +1,64 @@
-// XP Cost Formula: 100 + 10 * (Level - 1)^2.8, rounded to nearest u128
-// Index 'i' corresponds to the cost to reach Level 'i + 2' from Level 'i + 1'.
-pub const LEVEL_UP_EXPERIENCE: [u128; 99] = [
-    110,      // Level 1 -> 2
-    170,      // Level 2 -> 3
-    314,      // Level 3 -> 4
-    576,      // Level 4 -> 5
-    995,      // Level 5 -> 6
-    1611,     // Level 6 -> 7
-    2470,     // Level 7 -> 8
-    3621,     // Level 8 -> 9
-    5118,     // Level 9 -> 10
-    7017,     // Level 10 -> 11
-    9379,     // Level 11 -> 12
-    12269,    // Level 12 -> 13
-    15757,    // Level 13 -> 14
-    19917,    // Level 14 -> 15
-    24825,    // Level 15 -> 16
-    30559,    // Level 16 -> 17
-    37199,    // Level 17 -> 18
-    44830,    // Level 18 -> 19
-    53539,    // Level 19 -> 20
-    63417,    // Level 20 -> 21
-    74557,    // Level 21 -> 22
-    87059,    // Level 22 -> 23
-    101025,   // Level 23 -> 24
-    116562,   // Level 24 -> 25
-    133777,   // Level 25 -> 26
-    152782,   // Level 26 -> 27
-    173690,   // Level 27 -> 28
-    196617,   // Level 28 -> 29
-    221680,   // Level 29 -> 30
-    249000,   // Level 30 -> 31
-    278697,   // Level 31 -> 32
-    310900,   // Level 32 -> 33
-    345737,   // Level 33 -> 34
-    383340,   // Level 34 -> 35
-    423843,   // Level 35 -> 36
-    467384,   // Level 36 -> 37
-    514104,   // Level 37 -> 38
-    564147,   // Level 38 -> 39
-    617658,   // Level 39 -> 40
-    674784,   // Level 40 -> 41
-    735677,   // Level 41 -> 42
-    800491,   // Level 42 -> 43
-    869384,   // Level 43 -> 44
-    942516,   // Level 44 -> 45
-    1020051,  // Level 45 -> 46
-    1102156,  // Level 46 -> 47
-    1188999,  // Level 47 -> 48
-    1280753,  // Level 48 -> 49
-    1377592,  // Level 49 -> 50
-    1479695,  // Level 50 -> 51
-    1587242,  // Level 51 -> 52
-    1700417,  // Level 52 -> 53
-    1819407,  // Level 53 -> 54
-    1944399,  // Level 54 -> 55
-    2075587,  // Level 55 -> 56
-    2213168,  // Level 56 -> 57
-    2357345,  // Level 57 -> 58
-    2508323,  // Level 58 -> 59
-    2666309,  // Level 59 -> 60
-    2831517,  // Level 60 -> 61
-    3004162,  // Level 61 -> 62
-    3184462,  // Level 62 -> 63
-    3372636,  // Level 63 -> 64
-    3568906,  // Level 64 -> 65
-    3773500,  // Level 65 -> 66
-    3986648,  // Level 66 -> 67
-    4208584,  // Level 67 -> 68
-    4439544,  // Level 68 -> 69
-    4679770,  // Level 69 -> 70
-    4929508,  // Level 70 -> 71
-    5188999,  // Level 71 -> 72
-    5458492,  // Level 72 -> 73
-    5738240,  // Level 73 -> 74
-    6028500,  // Level 74 -> 75
-    6329531,  // Level 75 -> 76
-    6641594,  // Level 76 -> 77
-    6964956,  // Level 77 -> 78
-    7299886,  // Level 78 -> 79
-    7646657,  // Level 79 -> 80
-    8005546,  // Level 80 -> 81
-    8376837,  // Level 81 -> 82
-    8760819,  // Level 82 -> 83
-    9157786,  // Level 83 -> 84
-    9568038,  // Level 84 -> 85
-    9991880,  // Level 85 -> 86
-    10429623, // Level 86 -> 87
-    10881583, // Level 87 -> 88
-    11348080, // Level 88 -> 89
-    11829440, // Level 89 -> 90
-    12326000, // Level 90 -> 91
-    12838098, // Level 91 -> 92
-    13366079, // Level 92 -> 93
-    13910294, // Level 93 -> 94
-    14471102, // Level 94 -> 95
-    15048868, // Level 95 -> 96
-    15643966, // Level 96 -> 97
-    16256775, // Level 97 -> 98
-    16887681, // Level 98 -> 99
-    17537078, // Level 99 -> 100
-];
-
 pub const ITEM_DATABASE_PATH: &str = "src/database/items.json";
-pub const QUEST_DATABASE_PATH: &str = "src/database/quests.json";
\ No newline at end of file
+pub const QUEST_DATABASE_PATH: &str = "src/database/quests.json";
+pub const ACTIVITY_DATABASE_PATH: &str = "src/database/activities.json";
+pub const RECIPE_DATABASE_PATH: &str = "src/database/recipes.json";
+pub const JOB_DATABASE_PATH: &str = "src/database/jobs.json";
+pub const XP_CURVE_DATABASE_PATH: &str = "src/database/xp_curves.json";
+pub const OFFLINE_CONFIG_PATH: &str = "src/database/offline.json";
+pub const VENDOR_DATABASE_PATH: &str = "src/database/vendors.json";
+
+// The player levels up on this curve from xp_curves.json with the experience of every job
+pub const PLAYER_XP_CURVE: u128 = 2;
+// Stats at level 1 and what each level adds
+pub const PLAYER_BASE_HEALTH: u32 = 100;
+pub const PLAYER_BASE_MANA: u32 = 100;
+pub const PLAYER_BASE_ATTACK_POWER: u32 = 1;
+pub const PLAYER_BASE_DEFENSE: u32 = 1;
+pub const HEALTH_PER_LEVEL: u32 = 10;
+pub const MANA_PER_LEVEL: u32 = 5;
+pub const ATTACK_POWER_PER_LEVEL: u32 = 1;
+pub const DEFENSE_PER_LEVEL: u32 = 1;
+
+// A job can be prestiged from this level, every prestige adds this much XP and bonus item
+// chance to the activities that train it
+pub const PRESTIGE_MIN_LEVEL: u8 = 50;
+pub const PRESTIGE_BONUS_PERCENT: u32 = 10;
+
+// Faster activity perks stop adding up here
+pub const MAX_ACTIVITY_SPEED_PERCENT: u32 = 75;
+
+// Loot rolls past this many at once get their expected share instead of being sampled
+pub const LOOT_SAMPLE_LIMIT: u128 = 10_000;
+// Seed of Player::new, new games are seeded from the clock
+pub const PLAYER_DEFAULT_SEED: u64 = 1;
+
+// Length of a simulation step, activity and recipe durations should be multiples of it
+pub const SIMULATION_TICK_MS: u64 = 100;
+// How often the window repaints while it is unfocused or minimized
+pub const BACKGROUND_REPAINT_INTERVAL_MS: u64 = 1000;
+
+// How long a headless run with goals simulates before giving up
+pub const HEADLESS_MAX_SECS: u64 = 365 * 86400;
+
+// Defaults for the balance simulator
+pub const SIMULATOR_HORIZON_SECS: u64 = 30 * 86400;
+pub const SIMULATOR_LEVELS: [u8; 11] = [5, 10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+
+pub const SAVES_DIRECTORY: &str = "saves";
+// Saves from before save slots existed are imported into a slot with this name
+pub const LEGACY_SAVE_PATH: &str = "save.json";
+pub const LEGACY_SLOT_NAME: &str = "default";
+// Number of previous saves kept as save.json.1, save.json.2, ...
+pub const SAVE_BACKUP_COUNT: usize = 3;
+pub const AUTOSAVE_INTERVAL_SECS: u64 = 60;
+// How far the system clock may drift from the monotonic clock before it counts as changed
+pub const CLOCK_DRIFT_TOLERANCE_SECS: u64 = 120;
+// Offline time is not counted for this many sessions after the clock was changed
+pub const CLOCK_TRUST_SESSIONS: u64 = 3;
+// Once this many gaps between sessions are recorded, a gap more than CLOCK_GAP_FACTOR times the
+// longest of them counts as the clock jumping forward, unless it is shorter than the minimum
+pub const CLOCK_GAP_HISTORY: usize = 5;
+pub const CLOCK_GAP_FACTOR: u64 = 4;
+pub const CLOCK_MIN_TRUSTED_GAP_SECS: u64 = 2 * 3600;
+// Number of sessions and clock anomalies kept in the save
+pub const LEDGER_HISTORY_LENGTH: usize = 20;
@@ -0,0 +1,302 @@
+use crate::inventory::Inventory;
+use crate::item::Item;
+use crate::job::Job;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct RecipeData {
+    pub id: u128,
+    pub name: String,
+    pub description: String,
+    pub inputs: Vec<Item>,
+    pub outputs: Vec<Item>,
+    pub job: u128,
+    pub required_level: u8,
+    pub duration_ms: u64,
+    pub experience: u128,
+}
+
+// A recipe being crafted, the inputs of the craft in progress are already consumed
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Crafting {
+    pub recipe_id: u128,
+    pub name: String,
+    pub duration_ms: u64,
+    pub timer_ms: u64,
+    pub remaining: u32,
+    pub job: u128,
+    pub experience: u128,
+    pub inputs: Vec<Item>,
+    pub outputs: Vec<Item>,
+}
+
+impl RecipeData {
+    pub fn can_craft(&self, jobs: &[Job], inventory: &Inventory) -> bool {
+        self.has_required_level(jobs) && inventory.has_items(&self.inputs)
+    }
+
+    pub fn has_required_level(&self, jobs: &[Job]) -> bool {
+        jobs.iter()
+            .find(|job| job.id == self.job)
+            .is_some_and(|job| job.level >= self.required_level)
+    }
+
+    // How many times this recipe can be crafted with the current inventory
+    pub fn max_craftable(&self, inventory: &Inventory) -> u32 {
+        inventory.count_sets(&self.inputs).min(u128::from(u32::MAX)) as u32
+    }
+}
+
+impl Crafting {
+    // Checks the requirements and consumes the inputs of the first craft
+    pub fn start(
+        recipe_data: &RecipeData,
+        quantity: u32,
+        jobs: &[Job],
+        inventory: &mut Inventory,
+    ) -> Result<Self, String> {
+        if quantity == 0 {
+            return Err("Cannot craft 0 items".to_string());
+        }
+        if inventory.count_room(&recipe_data.outputs) == 0 {
+            return Err(format!("No room for the {} crafted", recipe_data.name));
+        }
+        if !recipe_data.has_required_level(jobs) {
+            let job_name = jobs.iter().find(|job| job.id == recipe_data.job).map_or("an unknown job", |job| job.name.as_str());
+            return Err(format!(
+                "{} requires {} level {}",
+                recipe_data.name, job_name, recipe_data.required_level
+            ));
+        }
+        inventory.remove_items(&recipe_data.inputs)?;
+
+        Ok(Self {
+            recipe_id: recipe_data.id,
+            name: recipe_data.name.clone(),
+            duration_ms: recipe_data.duration_ms,
+            timer_ms: 0,
+            remaining: quantity,
+            job: recipe_data.job,
+            experience: recipe_data.experience,
+            inputs: recipe_data.inputs.clone(),
+            outputs: recipe_data.outputs.clone(),
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining == 0
+    }
+
+    // Completes every craft that fits in the elapsed time at once, limited by the quantity
+    // asked for, the inputs left in the inventory and the room for the outputs. Running out
+    // of room stops the crafting. Returns the number of crafts completed.
+    pub fn update(
+        &mut self,
+        elapsed_ms: u64,
+        jobs: &mut [Job],
+        inventory: &mut Inventory,
+    ) -> Result<u128, String> {
+        if self.is_finished() {
+            return Ok(0);
+        }
+        let total_ms = u128::from(self.timer_ms) + u128::from(elapsed_ms);
+        let duration_ms = u128::from(self.duration_ms.max(1));
+        // the inputs of the craft in progress are already consumed
+        let due = (total_ms / duration_ms)
+            .min(u128::from(self.remaining))
+            .min(inventory.count_sets(&self.inputs).saturating_add(1));
+        let crafts = due.min(inventory.count_room(&self.outputs));
+        self.timer_ms = (total_ms - crafts * duration_ms) as u64;
+
+        if crafts > 0 {
+            self.complete(crafts, jobs, inventory)?;
+        }
+        if crafts < due {
+            self.cancel(inventory);
+        }
+        Ok(crafts)
+    }
+
+    // Between 0 and 1, how far the current craft is
+    pub fn progress(&self) -> f32 {
+        self.timer_ms as f32 / self.duration_ms.max(1) as f32
+    }
+
+    // Gives back the inputs of the craft in progress
+    pub fn cancel(&mut self, inventory: &mut Inventory) {
+        if !self.is_finished() {
+            for input in &self.inputs {
+                inventory.return_item(input);
+            }
+        }
+        self.remaining = 0;
+        self.timer_ms = 0;
+    }
+
+    fn complete(&mut self, crafts: u128, jobs: &mut [Job], inventory: &mut Inventory) -> Result<(), String> {
+        let inputs: Vec<Item> = self.inputs.iter().map(|input| input.scaled(crafts - 1)).collect();
+        inventory.remove_items(&inputs)?;
+
+        match jobs.iter_mut().find(|j| j.id == self.job) {
+            Some(job) => job.add_experience(self.experience * crafts),
+            None => return Err(format!("Job not found: {}", self.job)),
+        }
+        // update leaves room for the outputs
+        for output in &self.outputs {
+            let overflow = inventory.add_item(&output.scaled(crafts));
+            debug_assert_eq!(overflow, 0);
+        }
+
+        self.remaining -= crafts as u32;
+        //consume the inputs for the next craft, stop if we ran out
+        if !self.is_finished() && inventory.remove_items(&self.inputs).is_err() {
+            self.remaining = 0;
+        }
+        if self.is_finished() {
+            self.timer_ms = 0;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Crafting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({} left)", self.name, self.remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+
+    fn jobs() -> Vec<Job> {
+        vec![Job::new(
+            WOODCUTTER,
+            "Woodcutter".to_string(),
+            String::new(),
+            String::new(),
+            0,
+            1,
+            standard_curve(),
+        )]
+    }
+
+    fn plank_recipe() -> RecipeData {
+        RecipeData {
+            id: 1,
+            name: "Plank".to_string(),
+            description: "Saw a log into planks".to_string(),
+            inputs: vec![Item::new(1, 2)],
+            outputs: vec![Item::new(4, 1)],
+            job: WOODCUTTER,
+            required_level: 1,
+            duration_ms: 5000,
+            experience: 30,
+        }
+    }
+
+    #[test]
+    fn test_crafting_start_consumes_inputs() {
+        let jobs = jobs();
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(&Item::new(1, 5)), 0);
+
+        let crafting = Crafting::start(&plank_recipe(), 2, &jobs, &mut inventory).unwrap();
+
+        assert_eq!(crafting.recipe_id, 1);
+        assert_eq!(crafting.remaining, 2);
+        assert_eq!(inventory.get_item_quantity(1), 3);
+    }
+
+    #[test]
+    fn test_crafting_start_fails_without_inputs_or_level() {
+        let jobs = jobs();
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(&Item::new(1, 1)), 0);
+        assert!(Crafting::start(&plank_recipe(), 1, &jobs, &mut inventory).is_err());
+        assert_eq!(inventory.get_item_quantity(1), 1);
+
+        let mut recipe = plank_recipe();
+        recipe.required_level = 2;
+        assert_eq!(inventory.add_item(&Item::new(1, 1)), 0);
+        assert!(Crafting::start(&recipe, 1, &jobs, &mut inventory).is_err());
+        assert_eq!(inventory.get_item_quantity(1), 2);
+    }
+
+    #[test]
+    fn test_crafting_update_produces_outputs() {
+        let mut jobs = jobs();
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(&Item::new(1, 4)), 0);
+        let mut crafting = Crafting::start(&plank_recipe(), 2, &jobs, &mut inventory).unwrap();
+
+        crafting.update(2500, &mut jobs, &mut inventory).unwrap();
+        assert_eq!(inventory.get_item_quantity(4), 0);
+
+        crafting.update(2500, &mut jobs, &mut inventory).unwrap();
+        assert_eq!(inventory.get_item_quantity(4), 1);
+        assert_eq!(inventory.get_item_quantity(1), 0);
+        assert_eq!(jobs[0].experience, 30);
+        assert_eq!(crafting.remaining, 1);
+
+        crafting.update(5000, &mut jobs, &mut inventory).unwrap();
+        assert_eq!(inventory.get_item_quantity(4), 2);
+        assert!(crafting.is_finished());
+    }
+
+    #[test]
+    fn test_crafting_update_from_time_elapsed_stops_when_out_of_inputs() {
+        let mut jobs = jobs();
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(&Item::new(1, 6)), 0);
+        let mut crafting = Crafting::start(&plank_recipe(), 10, &jobs, &mut inventory).unwrap();
+
+        crafting
+            .update(1_000_000, &mut jobs, &mut inventory)
+            .unwrap();
+
+        assert!(crafting.is_finished());
+        assert_eq!(inventory.get_item_quantity(4), 3);
+        assert_eq!(inventory.get_item_quantity(1), 0);
+        assert_eq!(jobs[0].experience, 90);
+    }
+
+    #[test]
+    fn test_crafting_stops_at_the_stack_limit() {
+        let mut jobs = jobs();
+        let mut inventory = Inventory::new();
+        inventory.stack_limits.insert(4, 3);
+        assert_eq!(inventory.add_item(&Item::new(1, 20)), 0);
+        assert_eq!(inventory.add_item(&Item::new(4, 1)), 0);
+        let mut crafting = Crafting::start(&plank_recipe(), 10, &jobs, &mut inventory).unwrap();
+
+        crafting.update(1_000_000, &mut jobs, &mut inventory).unwrap();
+
+        // two planks fit, the inputs of the third craft are given back
+        assert!(crafting.is_finished());
+        assert_eq!(inventory.get_item_quantity(4), 3);
+        assert_eq!(inventory.get_item_quantity(1), 16);
+        assert_eq!(jobs[0].experience, 60);
+        assert!(Crafting::start(&plank_recipe(), 1, &jobs, &mut inventory).is_err());
+        assert_eq!(inventory.get_item_quantity(1), 16);
+    }
+
+    #[test]
+    fn test_crafting_cancel_refunds_inputs() {
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(&Item::new(1, 2)), 0);
+        let mut crafting = Crafting::start(&plank_recipe(), 1, &jobs(), &mut inventory).unwrap();
+        assert_eq!(inventory.get_item_quantity(1), 0);
+
+        // the refund goes past the stack limit rather than being lost
+        inventory.stack_limits.insert(1, 1);
+        assert_eq!(inventory.add_item(&Item::new(1, 1)), 0);
+        crafting.cancel(&mut inventory);
+
+        assert!(crafting.is_finished());
+        assert_eq!(inventory.get_item_quantity(1), 3);
+    }
+}
@@ -0,0 +1,170 @@
+// Equipment is defined on the items in items.json. Equipping an item takes it out of the
+// inventory and keeps a copy of its effects on the player, like activities and crafts do.
+
+use crate::item::ItemData;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EquipmentSlot {
+    Axe,
+    Pickaxe,
+    Hoe,
+    Armor,
+}
+
+// Activity effects apply to the activities that train the job, in percent
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum EquipmentEffect {
+    // shorter activity cycles
+    FasterActivity { job: u128, percent: u32 },
+    // chance of getting the items of a cycle twice
+    ExtraYield { job: u128, percent: u32 },
+    AttackPower(u32),
+    Defense(u32),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EquipmentData {
+    pub slot: EquipmentSlot,
+    pub effects: Vec<EquipmentEffect>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Equipment {
+    pub item_id: u128,
+    pub name: String,
+    pub slot: EquipmentSlot,
+    pub effects: Vec<EquipmentEffect>,
+}
+
+impl Equipment {
+    pub fn from_data(item_data: &ItemData) -> Result<Self, String> {
+        match &item_data.equipment {
+            Some(equipment_data) => Ok(Self {
+                item_id: item_data.id,
+                name: item_data.name.clone(),
+                slot: equipment_data.slot,
+                effects: equipment_data.effects.clone(),
+            }),
+            None => Err(format!("{} can not be equipped", item_data.name)),
+        }
+    }
+
+    pub fn faster_activity_percent(&self, job_id: u128) -> u32 {
+        self.effects
+            .iter()
+            .map(|effect| match *effect {
+                EquipmentEffect::FasterActivity { job, percent } if job == job_id => percent,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn extra_yield_percent(&self, job_id: u128) -> u32 {
+        self.effects
+            .iter()
+            .map(|effect| match *effect {
+                EquipmentEffect::ExtraYield { job, percent } if job == job_id => percent,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn attack_power(&self) -> u32 {
+        self.effects
+            .iter()
+            .map(|effect| match *effect {
+                EquipmentEffect::AttackPower(amount) => amount,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn defense(&self) -> u32 {
+        self.effects
+            .iter()
+            .map(|effect| match *effect {
+                EquipmentEffect::Defense(amount) => amount,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+impl fmt::Display for EquipmentSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Display for EquipmentEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FasterActivity { percent, .. } => write!(f, "{}% faster", percent),
+            Self::ExtraYield { percent, .. } => write!(f, "{}% chance of bonus items", percent),
+            Self::AttackPower(amount) => write!(f, "+{} attack power", amount),
+            Self::Defense(amount) => write!(f, "+{} defense", amount),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::item::ItemCategory;
+    use crate::job::mock::*;
+
+    pub const COPPER_AXE: u128 = 11;
+    pub const COPPER_ARMOR: u128 = 14;
+
+    pub fn copper_axe() -> ItemData {
+        ItemData {
+            equipment: Some(EquipmentData {
+                slot: EquipmentSlot::Axe,
+                effects: vec![
+                    EquipmentEffect::FasterActivity { job: WOODCUTTER, percent: 20 },
+                    EquipmentEffect::ExtraYield { job: WOODCUTTER, percent: 50 },
+                ],
+            }),
+            ..ItemData::new(COPPER_AXE, "Copper Axe", "", ItemCategory::Tool)
+        }
+    }
+
+    pub fn copper_armor() -> ItemData {
+        ItemData {
+            equipment: Some(EquipmentData {
+                slot: EquipmentSlot::Armor,
+                effects: vec![EquipmentEffect::Defense(5), EquipmentEffect::AttackPower(1)],
+            }),
+            ..ItemData::new(COPPER_ARMOR, "Copper Armor", "", ItemCategory::Armor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::*;
+    use crate::item::ItemCategory;
+    use crate::job::mock::*;
+
+    #[test]
+    fn test_equipment_effects() {
+        let axe = Equipment::from_data(&copper_axe()).unwrap();
+
+        assert_eq!(axe.slot, EquipmentSlot::Axe);
+        assert_eq!(axe.faster_activity_percent(WOODCUTTER), 20);
+        assert_eq!(axe.faster_activity_percent(MINER), 0);
+        assert_eq!(axe.extra_yield_percent(WOODCUTTER), 50);
+        assert_eq!(axe.defense(), 0);
+        assert_eq!(Equipment::from_data(&copper_armor()).unwrap().defense(), 5);
+    }
+
+    #[test]
+    fn test_equipment_from_data_needs_equipment() {
+        let log = ItemData::new(1, "Log", "", ItemCategory::Resource);
+
+        assert!(Equipment::from_data(&log).is_err());
+    }
+}
@@ -13,6 +13,8 @@ pub enum GameState {
     Crafting,
     Inventory,
     Quest(QuestState),
+    Prestige,
+    Shop,
 }
 
 impl GameState {
@@ -28,6 +30,8 @@ impl fmt::Display for GameState {
             Self::Crafting => write!(f, "Crafting"),
             Self::Inventory => write!(f, "Inventory"),
             Self::Quest(_) => write!(f, "Quest"),
+            Self::Prestige => write!(f, "Prestige"),
+            Self::Shop => write!(f, "Shop"),
         }
     }
 }
@@ -0,0 +1,318 @@
+// Runs the game without a window: loads a save slot, advances the simulation for a while or
+// until some goals are reached, saves it again and prints what happened.
+//
+// idle_rust --headless --slot <name> [--activity <id>] [--for <duration>] [--until <goal>]...
+//
+// --activity switches to the activity with that id in the activity database before running.
+// Goals are job:<job>:<level>, item:<item id>:<quantity> or quest:<quest id>. The run stops
+// as soon as every goal is reached, or after --for (HEADLESS_MAX_SECS by default).
+
+use crate::activity::Activity;
+use crate::constants::HEADLESS_MAX_SECS;
+use crate::constants::SAVES_DIRECTORY;
+use crate::constants::SAVE_BACKUP_COUNT;
+use crate::constants::SIMULATION_TICK_MS;
+use crate::game_state::GameState;
+use crate::ledger::SessionLedger;
+use crate::offline::OfflineConfig;
+use crate::offline::OfflineReport;
+use crate::player::Player;
+use crate::quest::Quest;
+use crate::save;
+use crate::save::SaveError;
+use crate::simulation::run_ticks_until;
+use crate::slots;
+use crate::utils::current_timestamp;
+use crate::utils::format_duration;
+use crate::utils::load_activity_database;
+use crate::utils::load_item_database;
+use crate::utils::load_job_database;
+use crate::utils::load_xp_curve_database;
+use crate::utils::load_offline_config;
+use crate::utils::load_quest_database;
+use crate::utils::parse_duration;
+use crate::utils::QuestDatabase;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Goal {
+    JobLevel(String, u8),
+    ItemQuantity(u128, u128),
+    Quest(u128),
+}
+
+impl Goal {
+    fn parse(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.split(':').collect();
+        let invalid = || format!("invalid goal: {}", text);
+        match parts.as_slice() {
+            ["job", job, level] => Ok(Self::JobLevel(job.to_string(), level.parse().map_err(|_| invalid())?)),
+            ["item", id, quantity] => Ok(Self::ItemQuantity(
+                id.parse().map_err(|_| invalid())?,
+                quantity.parse().map_err(|_| invalid())?,
+            )),
+            ["quest", id] => Ok(Self::Quest(id.parse().map_err(|_| invalid())?)),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn is_reached(&self, player: &Player, quests: &[Quest], quest_database: &QuestDatabase) -> bool {
+        match self {
+            Self::JobLevel(name, level) => player
+                .jobs
+                .iter()
+                .any(|job| job.name.to_string().eq_ignore_ascii_case(name) && job.level >= *level),
+            Self::ItemQuantity(id, quantity) => player.inventory.get_item_quantity(*id) >= *quantity,
+            Self::Quest(id) => quests.iter().any(|quest| {
+                quest.id == *id
+                    && (quest.completed
+                        || quest_database
+                            .get(id)
+                            .is_some_and(|quest_data| quest.check_completion(quest_data, player)))
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HeadlessOptions {
+    pub slot: String,
+    pub activity: Option<u128>,
+    pub duration: Option<u64>,
+    pub goals: Vec<Goal>,
+}
+
+// Parses the arguments after the program name
+pub fn parse_args(args: &[String]) -> Result<HeadlessOptions, String> {
+    let mut slot = None;
+    let mut activity = None;
+    let mut duration = None;
+    let mut goals = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--headless" => {}
+            "--slot" => slot = Some(value()?.clone()),
+            "--activity" => {
+                let id = value()?;
+                activity = Some(id.parse().map_err(|_| format!("invalid activity id: {}", id))?);
+            }
+            "--for" => duration = Some(parse_duration(value()?)?),
+            "--until" => goals.push(Goal::parse(value()?)?),
+            _ => return Err(format!("unknown argument: {}", arg)),
+        }
+    }
+
+    let slot = slot.ok_or_else(|| "--slot is required".to_string())?;
+    if !slots::is_valid_slot_name(&slot) {
+        return Err(format!("invalid slot name: {}", slot));
+    }
+    if duration.is_none() && goals.is_empty() {
+        return Err("nothing to do, give --for and/or --until".to_string());
+    }
+    Ok(HeadlessOptions { slot, activity, duration, goals })
+}
+
+// What a headless run did, on top of the offline progress applied when the save was loaded
+pub struct HeadlessRun {
+    pub time_simulated: u64,
+    pub goals_reached: bool,
+    pub report: OfflineReport,
+    pub quests_completed: Vec<u128>,
+}
+
+// Advances the player until every goal is reached or the time runs out, then completes the
+// quests that were asked for
+pub fn simulate(
+    player: &mut Player,
+    quests: &mut [Quest],
+    quest_database: &QuestDatabase,
+    options: &HeadlessOptions,
+) -> Result<HeadlessRun, String> {
+    let max_secs = options.duration.unwrap_or(HEADLESS_MAX_SECS);
+    let max_ticks = max_secs.saturating_mul(1000) / SIMULATION_TICK_MS;
+    let all_reached = |player: &Player| {
+        !options.goals.is_empty()
+            && options.goals.iter().all(|goal| goal.is_reached(player, quests, quest_database))
+    };
+
+    let before = player.clone();
+    let (progress, ticks) = run_ticks_until(player, max_ticks, all_reached)?;
+    let time_simulated = ticks * SIMULATION_TICK_MS / 1000;
+    let goals_reached = all_reached(player);
+    let report = OfflineReport::new(time_simulated, &OfflineConfig::unlimited(), progress, &before, player);
+
+    let mut quests_completed = vec![];
+    for goal in &options.goals {
+        if let Goal::Quest(id) = goal {
+            if !goal.is_reached(player, quests, quest_database) {
+                continue;
+            }
+            if let Some(quest) = quests.iter_mut().find(|quest| quest.id == *id && !quest.completed) {
+                quest.complete(player, quest_database);
+                if quest.completed {
+                    quests_completed.push(*id);
+                }
+            }
+        }
+    }
+
+    Ok(HeadlessRun { time_simulated, goals_reached, report, quests_completed })
+}
+
+// Loads the slot, applies offline progress like opening the game would, runs the simulation
+// and saves. Returns the text summary.
+pub fn run(options: &HeadlessOptions) -> Result<String, String> {
+    let item_database = load_item_database().map_err(|e| format!("Error loading item database: {}", e))?;
+    let quest_database = load_quest_database().map_err(|e| format!("Error loading quest database: {}", e))?;
+    let activity_database =
+        load_activity_database().map_err(|e| format!("Error loading activity database: {}", e))?;
+    let job_database = load_job_database().map_err(|e| format!("Error loading job database: {}", e))?;
+    let xp_curve_database =
+        load_xp_curve_database().map_err(|e| format!("Error loading XP curve database: {}", e))?;
+    let offline_config = load_offline_config().map_err(|e| format!("Error loading offline config: {}", e))?;
+
+    let save_name = slots::open_slot_path(SAVES_DIRECTORY, &options.slot).map_err(|e| e.to_string())?;
+    let (game_state, mut player, mut quests, mut ledger) = match save::load(&save_name) {
+        Ok(save) => (save.game_state, save.player, save.quests, save.ledger),
+        Err(SaveError::Missing) => (GameState::new(), Player::with_seed(current_timestamp()), vec![], SessionLedger::new()),
+        // never overwrite a save that could not be read
+        Err(e) => return Err(format!("Error loading {}: {}", save_name, e)),
+    };
+    player.update_data(&job_database, &xp_curve_database);
+    player.inventory.update_item_data(&item_database);
+    player.update_activity_data(&activity_database);
+    if quests.is_empty() {
+        quests = quest_database.values().map(|quest_data| Quest::new(quest_data.id, false)).collect();
+    }
+
+    let mut summary = vec![];
+    let clock_check = ledger.start_session(current_timestamp());
+    let mut offline_report = player.update_from_time_elapsed(clock_check.time_elapsed, &offline_config)?;
+    offline_report.clock_anomaly = clock_check.anomaly;
+    if let Some(clock_anomaly) = &offline_report.clock_anomaly {
+        summary.push(clock_anomaly.to_string());
+    }
+    if !offline_report.is_empty() {
+        summary.push(format!("Away for {}:", format_duration(offline_report.time_elapsed)));
+        summary.push(offline_report.summary(&item_database));
+    }
+
+    if let Some(id) = options.activity {
+        let activity_data = activity_database.get(&id).ok_or_else(|| format!("Activity not found: {}", id))?;
+        player.set_activity(Activity::from_data(activity_data));
+        summary.push(format!("Activity: {}", activity_data.name));
+    }
+
+    let run = simulate(&mut player, &mut quests, &quest_database, options)?;
+    summary.push(format!("Simulated {}:", format_duration(run.time_simulated)));
+    if !run.report.is_empty() {
+        summary.push(run.report.summary(&item_database));
+    }
+    for id in &run.quests_completed {
+        let name = quest_database.get(id).map_or("Unknown Quest", |quest_data| quest_data.name.as_str());
+        summary.push(format!("Quest completed: {}", name));
+    }
+    if !options.goals.is_empty() && !run.goals_reached {
+        summary.push("Goals not reached".to_string());
+    }
+
+    ledger.checkpoint(current_timestamp());
+    std::fs::create_dir_all(SAVES_DIRECTORY).map_err(|e| format!("Error creating {}: {}", SAVES_DIRECTORY, e))?;
+    save::save(&game_state, &player, &quests, &ledger, &save_name, SAVE_BACKUP_COUNT)
+        .map_err(|e| format!("Error saving {}: {}", save_name, e))?;
+    summary.push(format!("Saved {}", save_name));
+
+    Ok(summary.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+    use crate::item::Item;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn woodcutting_player() -> Player {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.set_activity(Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cut down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 10)],
+            vec![Item::new(1, 1)],
+        ));
+        player
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&args(
+            "--headless --slot Main --activity 2 --for 2h --until job:woodcutter:5 --until item:1:20",
+        ))
+        .unwrap();
+
+        assert_eq!(options.slot, "Main");
+        assert_eq!(options.activity, Some(2));
+        assert_eq!(options.duration, Some(7200));
+        assert_eq!(
+            options.goals,
+            vec![Goal::JobLevel("woodcutter".to_string(), 5), Goal::ItemQuantity(1, 20)]
+        );
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(&args("--headless --for 2h")).is_err());
+        assert!(parse_args(&args("--headless --slot Main")).is_err());
+        assert!(parse_args(&args("--headless --slot Main --for")).is_err());
+        assert!(parse_args(&args("--headless --slot Main --until job:woodcutter")).is_err());
+        assert!(parse_args(&args("--headless --slot ../Main --for 1h")).is_err());
+        assert!(parse_args(&args("--headless --slot Main --verbose")).is_err());
+        assert!(parse_args(&args("--headless --slot Main --activity wood --for 1h")).is_err());
+    }
+
+    #[test]
+    fn test_simulate_for_a_duration() {
+        let mut player = woodcutting_player();
+        let options = parse_args(&args("--slot Main --for 1h")).unwrap();
+
+        let run = simulate(&mut player, &mut [], &QuestDatabase::new(), &options).unwrap();
+
+        assert_eq!(run.time_simulated, 3600);
+        assert_eq!(run.report.cycles, 360);
+        assert_eq!(player.inventory.get_item_quantity(1), 360);
+    }
+
+    #[test]
+    fn test_simulate_until_goals() {
+        let mut player = woodcutting_player();
+        let options = parse_args(&args("--slot Main --until item:1:20 --until job:Woodcutter:2")).unwrap();
+
+        let run = simulate(&mut player, &mut [], &QuestDatabase::new(), &options).unwrap();
+
+        // level 2 takes 110 XP, so 11 logs, then 20 logs
+        assert!(run.goals_reached);
+        assert_eq!(run.time_simulated, 200);
+        assert_eq!(player.inventory.get_item_quantity(1), 20);
+    }
+
+    #[test]
+    fn test_simulate_gives_up_after_the_duration() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        let options = parse_args(&args("--slot Main --for 1d --until item:1:1")).unwrap();
+
+        let run = simulate(&mut player, &mut [], &QuestDatabase::new(), &options).unwrap();
+
+        assert!(!run.goals_reached);
+        assert_eq!(run.time_simulated, 86400);
+    }
+}
@@ -1,4 +1,6 @@
 use crate::item::Item;
+use crate::item::ItemCategory;
+use crate::utils::ItemDatabase;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
@@ -7,6 +9,16 @@ use std::fmt;
 pub struct Inventory {
     pub gold: u128,
     pub items: HashMap<u128, Item>,
+    // copied from the item database when the data is updated, only the items are saved
+    #[serde(skip)]
+    pub stack_limits: HashMap<u128, u128>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InventorySort {
+    Name,
+    Quantity,
+    Id,
 }
 
 impl Inventory {
@@ -14,15 +26,56 @@ impl Inventory {
         Self {
             gold: 0,
             items: HashMap::new(),
+            stack_limits: HashMap::new(),
         }
     }
 
-    pub fn add_item(&mut self, item: &Item) {
+    pub fn update_item_data(&mut self, item_database: &ItemDatabase) {
+        self.stack_limits = item_database
+            .values()
+            .filter_map(|item_data| Some((item_data.id, item_data.stack_limit?)))
+            .collect();
+    }
+
+    // Adds as many as the stack limit of the item allows and returns how many did not fit
+    #[must_use]
+    pub fn add_item(&mut self, item: &Item) -> u128 {
+        let added = item.quantity.min(self.room_for(item.id));
         if let Some(i) = self.items.get_mut(&item.id) {
-            i.quantity += item.quantity;
-        } else {
-            self.items.insert(item.id, item.clone());
+            i.quantity += added;
+        } else if added > 0 {
+            self.items.insert(item.id, Item::new(item.id, added));
         }
+        item.quantity - added
+    }
+
+    // Puts back items that were taken out of the inventory, even past their stack limit
+    pub fn return_item(&mut self, item: &Item) {
+        match self.items.get_mut(&item.id) {
+            Some(i) => i.quantity += item.quantity,
+            None if item.quantity > 0 => {
+                self.items.insert(item.id, item.clone());
+            }
+            None => {}
+        }
+    }
+
+    // How many more of the item fit under its stack limit
+    pub fn room_for(&self, item_id: u128) -> u128 {
+        match self.stack_limits.get(&item_id) {
+            Some(stack_limit) => stack_limit.saturating_sub(self.get_item_quantity(item_id)),
+            None => u128::MAX,
+        }
+    }
+
+    // How many times all the items fit in the inventory
+    pub fn count_room(&self, items: &[Item]) -> u128 {
+        items
+            .iter()
+            .filter(|item| item.quantity > 0)
+            .map(|item| self.room_for(item.id) / item.quantity)
+            .min()
+            .unwrap_or(u128::MAX)
     }
 
     pub fn remove_item(&mut self, item: Item) -> Result<(), String> {
@@ -41,6 +94,40 @@ impl Inventory {
         }
     }
 
+    pub fn has_items(&self, items: &[Item]) -> bool {
+        items
+            .iter()
+            .all(|item| self.get_item_quantity(item.id) >= item.quantity)
+    }
+
+    // How many times all the items can be taken from the inventory
+    pub fn count_sets(&self, items: &[Item]) -> u128 {
+        items
+            .iter()
+            .filter(|item| item.quantity > 0)
+            .map(|item| self.get_item_quantity(item.id) / item.quantity)
+            .min()
+            .unwrap_or(u128::MAX)
+    }
+
+    // Removes all the items or none of them
+    pub fn remove_items(&mut self, items: &[Item]) -> Result<(), String> {
+        if let Some(item) = items
+            .iter()
+            .find(|item| self.get_item_quantity(item.id) < item.quantity)
+        {
+            return Err(format!(
+                "Item {} has only {} left",
+                item.id,
+                self.get_item_quantity(item.id)
+            ));
+        }
+        for item in items.iter().filter(|item| item.quantity > 0) {
+            self.remove_item(item.clone())?;
+        }
+        Ok(())
+    }
+
     pub fn get_item(&self, item_id: u128) -> Option<&Item> {
         self.items.get(&item_id)
     }
@@ -49,6 +136,52 @@ impl Inventory {
         self.get_item(item_id).map_or(0, |i| i.quantity)
     }
 
+    // Items whose name, description or id contain the filter, sorted by the given key
+    pub fn list_items(
+        &self,
+        item_database: &ItemDatabase,
+        filter: &str,
+        category: Option<ItemCategory>,
+        sort: InventorySort,
+        ascending: bool,
+    ) -> Vec<&Item> {
+        let filter = filter.trim().to_lowercase();
+        let item_name = |item: &Item| {
+            item_database
+                .get(&item.id)
+                .map_or(String::new(), |data| data.name.to_lowercase())
+        };
+
+        let mut items: Vec<&Item> = self
+            .items
+            .values()
+            .filter(|item| match category {
+                Some(category) => item_database.get(&item.id).is_some_and(|data| data.category == category),
+                None => true,
+            })
+            .filter(|item| {
+                if filter.is_empty() || item.id.to_string().contains(&filter) {
+                    return true;
+                }
+                item_database.get(&item.id).is_some_and(|data| data.matches(&filter))
+            })
+            .collect();
+
+        match sort {
+            InventorySort::Name => {
+                items.sort_by(|a, b| item_name(a).cmp(&item_name(b)).then(a.id.cmp(&b.id)))
+            }
+            InventorySort::Quantity => {
+                items.sort_by(|a, b| a.quantity.cmp(&b.quantity).then(a.id.cmp(&b.id)))
+            }
+            InventorySort::Id => items.sort_by_key(|item| item.id),
+        }
+        if !ascending {
+            items.reverse();
+        }
+        items
+    }
+
     pub fn add_gold(&mut self, amount: u128) {
         self.gold += amount;
     }
@@ -71,3 +204,88 @@ impl fmt::Display for Inventory {
         Ok(())
     }
 }
+
+impl fmt::Display for InventorySort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::item::ItemData;
+
+    fn item_database() -> ItemDatabase {
+        let mut item_database = ItemDatabase::new();
+        for (id, name, description, category) in [
+            (1, "Log", "A Log.", ItemCategory::Resource),
+            (2, "Stone", "A chunk of rock.", ItemCategory::Resource),
+            (3, "Potato", "A potato.", ItemCategory::Food),
+        ] {
+            item_database.insert(id, ItemData::new(id, name, description, category));
+        }
+        item_database.get_mut(&3).unwrap().tags = vec!["vegetable".to_string()];
+        item_database
+    }
+
+    fn inventory() -> Inventory {
+        let mut inventory = Inventory::new();
+        for item in [Item::new(1, 5), Item::new(2, 20), Item::new(3, 1)] {
+            assert_eq!(inventory.add_item(&item), 0);
+        }
+        inventory
+    }
+
+    #[test]
+    fn test_add_item_stops_at_the_stack_limit() {
+        let mut item_database = item_database();
+        item_database.get_mut(&3).unwrap().stack_limit = Some(10);
+        let mut inventory = inventory();
+        inventory.update_item_data(&item_database);
+
+        assert_eq!(inventory.add_item(&Item::new(3, 4)), 0);
+        assert_eq!(inventory.add_item(&Item::new(3, 7)), 2);
+        assert_eq!(inventory.get_item_quantity(3), 10);
+        assert_eq!(inventory.add_item(&Item::new(1, 1000)), 0);
+        assert_eq!(inventory.get_item_quantity(1), 1005);
+        assert_eq!(inventory.count_room(&[Item::new(3, 1)]), 0);
+
+        // what was taken out can always be put back
+        inventory.remove_item(Item::new(3, 3)).unwrap();
+        assert_eq!(inventory.add_item(&Item::new(3, 5)), 2);
+        inventory.return_item(&Item::new(3, 3));
+        assert_eq!(inventory.get_item_quantity(3), 13);
+    }
+
+    #[test]
+    fn test_list_items_sorted() {
+        let item_database = item_database();
+        let inventory = inventory();
+
+        let ids = |items: Vec<&Item>| items.iter().map(|item| item.id).collect::<Vec<u128>>();
+        assert_eq!(ids(inventory.list_items(&item_database, "", None, InventorySort::Name, true)), vec![1, 3, 2]);
+        assert_eq!(ids(inventory.list_items(&item_database, "", None, InventorySort::Quantity, true)), vec![3, 1, 2]);
+        assert_eq!(ids(inventory.list_items(&item_database, "", None, InventorySort::Id, false)), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_list_items_filtered() {
+        let item_database = item_database();
+        let inventory = inventory();
+
+        let items = inventory.list_items(&item_database, "  POT ", None, InventorySort::Id, true);
+        assert_eq!(items, vec![&Item::new(3, 1)]);
+
+        let items = inventory.list_items(&item_database, "rock", None, InventorySort::Id, true);
+        assert_eq!(items, vec![&Item::new(2, 20)]);
+
+        let items = inventory.list_items(&item_database, "nothing", None, InventorySort::Id, true);
+        assert!(items.is_empty());
+
+        let items = inventory.list_items(&item_database, "vegetable", None, InventorySort::Id, true);
+        assert_eq!(items, vec![&Item::new(3, 1)]);
+
+        let items = inventory.list_items(&item_database, "", Some(ItemCategory::Resource), InventorySort::Id, true);
+        assert_eq!(items, vec![&Item::new(1, 5), &Item::new(2, 20)]);
+    }
+}
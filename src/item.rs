@@ -1,3 +1,4 @@
+use crate::equipment::EquipmentData;
 use serde::{Deserialize, Serialize};
 use std::clone::Clone;
 use std::cmp::PartialEq;
@@ -9,11 +10,44 @@ pub struct Item {
     pub quantity: u128,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ItemCategory {
+    // gathered by activities
+    Resource,
+    // crafted from resources for other recipes
+    Material,
+    Food,
+    Tool,
+    Armor,
+    Quest,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct ItemData {
     pub id: u128,
     pub name: String,
     pub description: String,
+    pub category: ItemCategory,
+    // extra words the inventory search matches
+    pub tags: Vec<String>,
+    pub rarity: Rarity,
+    // how many a player should hold at most, None for no limit
+    pub stack_limit: Option<u128>,
+    // gold a vendor pays for one
+    pub sell_value: u128,
+    // path to a png, the name is shown when it is missing
+    pub icon: Option<String>,
+    // set for the items that can be equipped
+    pub equipment: Option<EquipmentData>,
 }
 
 impl Item {
@@ -24,6 +58,11 @@ impl Item {
         }
     }
 
+    // The same item with its quantity multiplied, e.g. the rewards of several cycles at once
+    pub fn scaled(&self, factor: u128) -> Self {
+        Self::new(self.id, self.quantity * factor)
+    }
+
     pub fn add_quantity(&mut self, quantity: u128) {
         self.quantity += quantity;
     }
@@ -33,6 +72,42 @@ impl Item {
     }
 }
 
+impl ItemData {
+    // An item with no metadata beyond its category
+    pub fn new(id: u128, name: &str, description: &str, category: ItemCategory) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            description: description.to_string(),
+            category,
+            tags: vec![],
+            rarity: Rarity::Common,
+            stack_limit: None,
+            sell_value: 0,
+            icon: None,
+            equipment: None,
+        }
+    }
+
+    pub fn matches(&self, filter: &str) -> bool {
+        self.name.to_lowercase().contains(filter)
+            || self.description.to_lowercase().contains(filter)
+            || self.tags.iter().any(|tag| tag.to_lowercase().contains(filter))
+    }
+}
+
+impl fmt::Display for ItemCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 impl fmt::Display for ItemData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}: {}", self.name, self.description)
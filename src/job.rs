@@ -1,95 +1,302 @@
+use crate::constants::PRESTIGE_BONUS_PERCENT;
+use crate::constants::PRESTIGE_MIN_LEVEL;
+use crate::xp_curve::XpCurve;
 use serde::{Deserialize, Serialize};
 use std::cmp::PartialEq;
 use std::fmt;
 
+// What a perk does to the activities that train its job, in percent
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum PerkEffect {
+    // shorter activity cycles
+    FasterActivity(u32),
+    // chance of getting the items of a cycle twice
+    BonusItemChance(u32),
+    ExtraExperience(u32),
+}
+
+impl fmt::Display for PerkEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FasterActivity(percent) => write!(f, "{}% faster", percent),
+            Self::BonusItemChance(percent) => write!(f, "{}% chance of bonus items", percent),
+            Self::ExtraExperience(percent) => write!(f, "{}% more XP", percent),
+        }
+    }
+}
+
+// Unlocked once the job reaches the level
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
-pub struct Job {
-    pub name: JobName,
-    pub description: String,
+pub struct Perk {
     pub level: u8,
-    pub experience: u128,
-    pub level_up_experience: Vec<u128>,
+    pub name: String,
+    pub effect: PerkEffect,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
-pub enum JobName {
-    Woodcutter,
-    Miner,
-    Farmer,
+// Jobs are defined in jobs.json, activities, recipes and quests refer to them by id
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct JobData {
+    pub id: u128,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    // id in xp_curves.json
+    pub xp_curve: u128,
+    pub perks: Vec<Perk>,
 }
 
-impl fmt::Display for JobName {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Job {
+    pub id: u128,
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub level: u8,
+    // at the max level the experience keeps adding up without leveling
+    pub experience: u128,
+    // times the job was reset for a permanent bonus, and the experience it had then
+    pub prestige: u32,
+    pub prestige_experience: u128,
+    pub xp_curve: u128,
+    // looked up from xp_curve when the jobs are updated, only the id is saved
+    #[serde(skip)]
+    pub curve: XpCurve,
+    // copied from the job database when the jobs are updated
+    #[serde(skip)]
+    pub perks: Vec<Perk>,
 }
 
 impl Job {
     pub fn new(
-        name: JobName,
+        id: u128,
+        name: String,
         description: String,
+        icon: String,
         experience: u128,
         level: u8,
-        level_up_experience: Vec<u128>,
+        curve: XpCurve,
     ) -> Self {
         Self {
+            id,
             name,
             description,
+            icon,
             experience,
             level,
-            level_up_experience,
+            prestige: 0,
+            prestige_experience: 0,
+            xp_curve: curve.id,
+            curve,
+            perks: vec![],
         }
     }
 
+    // A new job at level 1
+    pub fn from_data(job_data: &JobData, curve: &XpCurve) -> Self {
+        let mut job = Self::new(
+            job_data.id,
+            job_data.name.clone(),
+            job_data.description.clone(),
+            job_data.icon.clone(),
+            0,
+            1,
+            curve.clone(),
+        );
+        job.perks = job_data.perks.clone();
+        job
+    }
+
+    // Picks up changes to the name, description, icon, XP curve and perks in the databases
+    pub fn update_data(&mut self, job_data: &JobData, curve: &XpCurve) {
+        self.name = job_data.name.clone();
+        self.description = job_data.description.clone();
+        self.icon = job_data.icon.clone();
+        self.perks = job_data.perks.clone();
+        self.xp_curve = curve.id;
+        self.curve = curve.clone();
+        self.level_up();
+    }
+
     pub fn add_experience(&mut self, amount: u128) {
-        self.experience += amount;
-        if self.experience >= self.level_up_experience[self.level as usize - 1] {
-            self.level_up();
-        }
+        self.experience = self.experience.saturating_add(amount);
+        self.level_up();
     }
 
     //need to loop in case we need to level up multiple times
     fn level_up(&mut self) {
-        while self.experience >= self.level_up_experience[self.level as usize - 1] {
-            self.experience -= self.level_up_experience[self.level as usize - 1];
+        while let Some(needed) = self.curve.experience_to_next(self.level) {
+            if self.experience < needed {
+                break;
+            }
+            self.experience -= needed;
             self.level += 1;
         }
     }
 
-    pub fn get_xp_needed_for_next_level(&self) -> u128 {
-        self.level_up_experience[self.level as usize - 1] - self.experience
+    pub fn unlocked_perks(&self) -> impl Iterator<Item = &Perk> {
+        self.perks.iter().filter(|perk| perk.level <= self.level)
+    }
+
+    pub fn can_prestige(&self) -> bool {
+        self.level >= PRESTIGE_MIN_LEVEL
+    }
+
+    // Starts the job over at level 1 for a permanent bonus
+    pub fn prestige(&mut self) -> Result<(), String> {
+        if !self.can_prestige() {
+            return Err(format!("{} needs level {} to prestige", self.name, PRESTIGE_MIN_LEVEL));
+        }
+        self.prestige_experience += self.total_experience();
+        self.prestige += 1;
+        self.level = 1;
+        self.experience = 0;
+        Ok(())
+    }
+
+    pub fn prestige_bonus_percent(&self) -> u32 {
+        self.prestige * PRESTIGE_BONUS_PERCENT
+    }
+
+    // The unlocked perks of each kind add up, the prestige bonus adds to the XP and bonus
+    // item chance
+    pub fn faster_activity_percent(&self) -> u32 {
+        self.unlocked_perks()
+            .map(|perk| match perk.effect {
+                PerkEffect::FasterActivity(percent) => percent,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    pub fn bonus_item_percent(&self) -> u32 {
+        self.unlocked_perks()
+            .map(|perk| match perk.effect {
+                PerkEffect::BonusItemChance(percent) => percent,
+                _ => 0,
+            })
+            .sum::<u32>()
+            + self.prestige_bonus_percent()
+    }
+
+    pub fn extra_experience_percent(&self) -> u32 {
+        self.unlocked_perks()
+            .map(|perk| match perk.effect {
+                PerkEffect::ExtraExperience(percent) => percent,
+                _ => 0,
+            })
+            .sum::<u32>()
+            + self.prestige_bonus_percent()
+    }
+
+    // Experience earned since level 1
+    pub fn total_experience(&self) -> u128 {
+        self.curve.experience_to_reach(self.level) + self.experience
+    }
+
+    // None at the max level
+    pub fn get_xp_needed_for_next_level(&self) -> Option<u128> {
+        self.curve
+            .experience_to_next(self.level)
+            .map(|needed| needed.saturating_sub(self.experience))
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::utils::JobDatabase;
+    use crate::xp_curve::mock::*;
+
+    pub const WOODCUTTER: u128 = 1;
+    pub const MINER: u128 = 2;
+    pub const FARMER: u128 = 3;
+
+    pub fn job_database() -> JobDatabase {
+        let mut job_database = JobDatabase::new();
+        for (id, name, description) in [
+            (WOODCUTTER, "Woodcutter", "Cut down trees"),
+            (MINER, "Miner", "Mine rocks"),
+            (FARMER, "Farmer", "Grow crops"),
+        ] {
+            let icon = String::new();
+            let job_data = JobData {
+                id,
+                name: name.to_string(),
+                description: description.to_string(),
+                icon,
+                xp_curve: STANDARD,
+                perks: vec![],
+            };
+            job_database.insert(id, job_data);
+        }
+        job_database
+    }
+
+    // The three jobs at level 1, sorted by id
+    pub fn jobs() -> Vec<Job> {
+        let mut jobs: Vec<Job> =
+            job_database().values().map(|job_data| Job::from_data(job_data, &standard_curve())).collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
-    use crate::constants::LEVEL_UP_EXPERIENCE;
+    use crate::xp_curve::mock::*;
+    use crate::xp_curve::XpFormula;
+    use mock::*;
+
+    fn woodcutter() -> Job {
+        Job::from_data(&job_database()[&WOODCUTTER], &standard_curve())
+    }
 
     #[test]
     fn test_job_new() {
         let job = Job::new(
-            JobName::Woodcutter,
+            WOODCUTTER,
             "Woodcutter".to_string(),
+            "Cut down trees".to_string(),
+            "🌲".to_string(),
             0,
             1,
-            LEVEL_UP_EXPERIENCE.to_vec(),
+            standard_curve(),
         );
-        assert_eq!(job.name, JobName::Woodcutter);
-        assert_eq!(job.description, "Woodcutter");
+        assert_eq!(job.id, WOODCUTTER);
+        assert_eq!(job.name, "Woodcutter");
+        assert_eq!(job.description, "Cut down trees");
+        assert_eq!(job.icon, "🌲");
         assert_eq!(job.experience, 0);
         assert_eq!(job.level, 1);
-        assert_eq!(job.level_up_experience, LEVEL_UP_EXPERIENCE.to_vec());
+        assert_eq!(job.xp_curve, STANDARD);
+    }
+
+    #[test]
+    fn test_job_from_data() {
+        let mut job = Job::from_data(&job_database()[&MINER], &standard_curve());
+        assert_eq!(job.name, "Miner");
+        assert_eq!(job.level, 1);
+        assert_eq!(job.experience, 0);
+
+        let job_data = JobData {
+            id: MINER,
+            name: "Prospector".to_string(),
+            description: String::new(),
+            icon: "⛏".to_string(),
+            xp_curve: STANDARD,
+            perks: vec![],
+        };
+        job.add_experience(10);
+        job.update_data(&job_data, &standard_curve());
+        assert_eq!(job.name, "Prospector");
+        assert_eq!(job.icon, "⛏");
+        assert_eq!(job.experience, 10);
     }
 
     #[test]
     fn test_job_add_experience() {
-        let mut job = Job::new(
-            JobName::Woodcutter,
-            "Woodcutter".to_string(),
-            0,
-            1,
-            LEVEL_UP_EXPERIENCE.to_vec(),
-        );
+        let mut job = woodcutter();
         job.add_experience(50);
         assert_eq!(job.experience, 50);
         assert_eq!(job.level, 1);
@@ -97,14 +304,8 @@ mod tests {
 
     #[test]
     fn test_job_level_up() {
-        let mut job = Job::new(
-            JobName::Woodcutter,
-            "Woodcutter".to_string(),
-            0,
-            1,
-            LEVEL_UP_EXPERIENCE.to_vec(),
-        );
-        let xp_needed_for_level_2 = LEVEL_UP_EXPERIENCE[0];
+        let mut job = woodcutter();
+        let xp_needed_for_level_2 = level_up_experience(1);
         job.add_experience(xp_needed_for_level_2 - 1);
         assert_eq!(job.level, 1);
         assert_eq!(job.experience, xp_needed_for_level_2 - 1);
@@ -115,17 +316,62 @@ mod tests {
 
     #[test]
     fn test_job_level_up_multiple_times() {
-        let mut job = Job::new(
-            JobName::Woodcutter,
-            "Woodcutter".to_string(),
-            0,
-            1,
-            LEVEL_UP_EXPERIENCE.to_vec(),
-        );
-        job.add_experience(
-            LEVEL_UP_EXPERIENCE[0] + LEVEL_UP_EXPERIENCE[1] + LEVEL_UP_EXPERIENCE[2] + 10,
-        );
+        let mut job = woodcutter();
+        job.add_experience(level_up_experience(1) + level_up_experience(2) + level_up_experience(3) + 10);
         assert_eq!(job.level, 4);
         assert_eq!(job.experience, 10);
+        assert_eq!(job.total_experience(), level_up_experience(1) + level_up_experience(2) + level_up_experience(3) + 10);
+    }
+
+    #[test]
+    fn test_job_perks_unlock_with_level() {
+        let mut job = woodcutter();
+        job.perks = vec![
+            Perk { level: 2, name: "Sharpened Axe".to_string(), effect: PerkEffect::FasterActivity(10) },
+            Perk { level: 3, name: "Clean Cuts".to_string(), effect: PerkEffect::FasterActivity(15) },
+            Perk { level: 3, name: "Insight".to_string(), effect: PerkEffect::ExtraExperience(20) },
+        ];
+        assert_eq!(job.unlocked_perks().count(), 0);
+
+        job.add_experience(level_up_experience(1) + level_up_experience(2));
+
+        assert_eq!(job.unlocked_perks().count(), 3);
+        assert_eq!(job.faster_activity_percent(), 25);
+        assert_eq!(job.extra_experience_percent(), 20);
+        assert_eq!(job.bonus_item_percent(), 0);
+    }
+
+    #[test]
+    fn test_job_prestige() {
+        let mut job = woodcutter();
+        job.perks = vec![Perk { level: 2, name: "Insight".to_string(), effect: PerkEffect::ExtraExperience(5) }];
+        job.level = PRESTIGE_MIN_LEVEL - 1;
+        assert!(job.prestige().is_err());
+
+        job.add_experience(level_up_experience(1));
+        job.level = PRESTIGE_MIN_LEVEL;
+        let total_experience = job.total_experience();
+        job.prestige().unwrap();
+
+        assert_eq!(job.level, 1);
+        assert_eq!(job.experience, 0);
+        assert_eq!(job.prestige, 1);
+        assert_eq!(job.prestige_experience, total_experience);
+        // the perks are lost until the job levels up again, the prestige bonus stays
+        assert_eq!(job.extra_experience_percent(), PRESTIGE_BONUS_PERCENT);
+        assert_eq!(job.bonus_item_percent(), PRESTIGE_BONUS_PERCENT);
+    }
+
+    #[test]
+    fn test_job_max_level_keeps_overflow_experience() {
+        let curve = XpCurve { id: 2, name: "Short".to_string(), max_level: 3, formula: XpFormula::Table(vec![100, 200]) };
+        let mut job = Job::new(WOODCUTTER, "Woodcutter".to_string(), String::new(), String::new(), 0, 1, curve);
+
+        job.add_experience(1000);
+
+        assert_eq!(job.level, 3);
+        assert_eq!(job.experience, 700);
+        assert_eq!(job.get_xp_needed_for_next_level(), None);
+        assert_eq!(job.total_experience(), 1000);
     }
 }
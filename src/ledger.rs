@@ -0,0 +1,360 @@
+// Keeps track of when the game was played so offline progress does not blindly trust the
+// system clock. Times are unix seconds, play time is measured with a monotonic clock.
+
+use crate::constants::CLOCK_DRIFT_TOLERANCE_SECS;
+use crate::constants::CLOCK_GAP_FACTOR;
+use crate::constants::CLOCK_GAP_HISTORY;
+use crate::constants::CLOCK_MIN_TRUSTED_GAP_SECS;
+use crate::constants::CLOCK_TRUST_SESSIONS;
+use crate::constants::LEDGER_HISTORY_LENGTH;
+use crate::utils::format_duration;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Instant;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum ClockAnomalyKind {
+    // the clock was behind the latest time the game had seen
+    WentBackwards,
+    // the clock fell behind the monotonic clock while the game was running
+    ChangedDuringSession,
+    // offline time that was not counted because the clock was changed recently, or because it
+    // was far longer than the time between the recorded sessions
+    ForwardJumpIgnored,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct ClockAnomaly {
+    pub kind: ClockAnomalyKind,
+    // number of the session it happened in
+    pub session: u64,
+    pub seconds: u64,
+}
+
+impl fmt::Display for ClockAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = format_duration(self.seconds);
+        match self.kind {
+            ClockAnomalyKind::WentBackwards => write!(
+                f,
+                "Your system clock is {} behind the last time you played. Offline progress resumes once it catches up.",
+                seconds
+            ),
+            ClockAnomalyKind::ChangedDuringSession => {
+                write!(f, "Your system clock changed by {} while the game was running.", seconds)
+            }
+            ClockAnomalyKind::ForwardJumpIgnored => write!(
+                f,
+                "Your system clock looks like it was moved forward, so {} of offline time was not counted.",
+                seconds
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Session {
+    pub start: u64,
+    pub end: u64,
+}
+
+// The result of checking the clock when a session starts
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClockCheck {
+    pub time_elapsed: u64,
+    pub anomaly: Option<ClockAnomaly>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct ActiveSession {
+    started: Instant,
+    start: u64,
+    // latest time seen once the session started
+    latest_at_start: u64,
+    play_time_at_start: u64,
+    drift_reported: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SessionLedger {
+    // time of the last checkpoint, by the monotonic clock
+    pub last_seen: u64,
+    // latest time the game has ever seen, offline progress is only counted past it
+    pub latest_seen: u64,
+    pub play_time_secs: u64,
+    pub session_count: u64,
+    // the most recent sessions and anomalies, oldest first
+    pub sessions: Vec<Session>,
+    pub anomalies: Vec<ClockAnomaly>,
+    #[serde(skip)]
+    active: Option<ActiveSession>,
+}
+
+impl SessionLedger {
+    pub fn new() -> Self {
+        Self {
+            last_seen: 0,
+            latest_seen: 0,
+            play_time_secs: 0,
+            session_count: 0,
+            sessions: vec![],
+            anomalies: vec![],
+            active: None,
+        }
+    }
+
+    // The clock is not trusted for a few sessions after it was seen going backwards or
+    // changing while the game was running
+    pub fn is_clock_trusted(&self) -> bool {
+        !self.anomalies.iter().any(|anomaly| {
+            anomaly.kind != ClockAnomalyKind::ForwardJumpIgnored
+                && self.session_count - anomaly.session <= CLOCK_TRUST_SESSIONS
+        })
+    }
+
+    // Starts a new session and returns how much offline time should be counted
+    pub fn start_session(&mut self, now: u64) -> ClockCheck {
+        let is_new_game = self.session_count == 0 && self.latest_seen == 0;
+        let mut check = ClockCheck { time_elapsed: 0, anomaly: None };
+        let gap = now.saturating_sub(self.latest_seen);
+        let max_gap = self.max_trusted_gap();
+        self.session_count += 1;
+
+        match max_gap {
+            _ if is_new_game => {}
+            _ if now < self.latest_seen => {
+                check.anomaly = Some(self.record_anomaly(ClockAnomalyKind::WentBackwards, self.latest_seen - now));
+            }
+            _ if !self.is_clock_trusted() && gap > CLOCK_DRIFT_TOLERANCE_SECS => {
+                check.anomaly = Some(self.record_anomaly(ClockAnomalyKind::ForwardJumpIgnored, gap));
+            }
+            // only the longest gap the history makes believable is counted
+            Some(max_gap) if gap > max_gap => {
+                check.time_elapsed = max_gap;
+                check.anomaly = Some(self.record_anomaly(ClockAnomalyKind::ForwardJumpIgnored, gap - max_gap));
+            }
+            _ => check.time_elapsed = gap,
+        }
+
+        self.last_seen = now;
+        self.latest_seen = self.latest_seen.max(now);
+        self.sessions.push(Session { start: now, end: now });
+        if self.sessions.len() > LEDGER_HISTORY_LENGTH {
+            self.sessions.remove(0);
+        }
+        self.active = Some(ActiveSession {
+            started: Instant::now(),
+            start: now,
+            latest_at_start: self.latest_seen,
+            play_time_at_start: self.play_time_secs,
+            drift_reported: false,
+        });
+        check
+    }
+
+    // The time by the ledger, for timers that must not follow the system clock: the latest time
+    // seen when the session started plus the time played since, by the monotonic clock
+    pub fn now(&self) -> u64 {
+        match &self.active {
+            Some(active) => active.latest_at_start + active.started.elapsed().as_secs(),
+            None => self.latest_seen,
+        }
+    }
+
+    // None until enough sessions are recorded to tell what a usual gap between them is
+    fn max_trusted_gap(&self) -> Option<u64> {
+        let gaps: Vec<u64> =
+            self.sessions.windows(2).map(|pair| pair[1].start.saturating_sub(pair[0].end)).collect();
+        if gaps.len() < CLOCK_GAP_HISTORY {
+            return None;
+        }
+        let longest = gaps.iter().copied().max().unwrap_or(0);
+        Some(longest.saturating_mul(CLOCK_GAP_FACTOR).max(CLOCK_MIN_TRUSTED_GAP_SECS))
+    }
+
+    // Brings the ledger up to date before saving
+    pub fn checkpoint(&mut self, now: u64) {
+        if let Some(active) = &self.active {
+            let session_secs = active.started.elapsed().as_secs();
+            self.record(now, session_secs);
+        }
+    }
+
+    // The end of the session is measured from its start with the monotonic clock, so changing
+    // the clock while playing does not move it. The monotonic clock stops while the system is
+    // suspended, so only a clock behind it is reported, not one ahead of it.
+    fn record(&mut self, now: u64, session_secs: u64) {
+        let (start, play_time_at_start, drift_reported) = match &self.active {
+            Some(active) => (active.start, active.play_time_at_start, active.drift_reported),
+            None => return,
+        };
+        let end = start + session_secs;
+
+        if !drift_reported && now + CLOCK_DRIFT_TOLERANCE_SECS < end {
+            self.record_anomaly(ClockAnomalyKind::ChangedDuringSession, end - now);
+            if let Some(active) = &mut self.active {
+                active.drift_reported = true;
+            }
+        }
+
+        self.last_seen = end;
+        self.latest_seen = self.latest_seen.max(end);
+        self.play_time_secs = play_time_at_start + session_secs;
+        if let Some(session) = self.sessions.last_mut() {
+            session.end = end;
+        }
+    }
+
+    fn record_anomaly(&mut self, kind: ClockAnomalyKind, seconds: u64) -> ClockAnomaly {
+        let anomaly = ClockAnomaly { kind, session: self.session_count, seconds };
+        println!("Clock anomaly: {}", anomaly);
+        self.anomalies.push(anomaly);
+        if self.anomalies.len() > LEDGER_HISTORY_LENGTH {
+            self.anomalies.remove(0);
+        }
+        anomaly
+    }
+}
+
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 3600;
+
+    // A ledger that has been played once, for an hour starting at 1_000_000
+    fn played_ledger() -> SessionLedger {
+        let mut ledger = SessionLedger::new();
+        ledger.start_session(1_000_000);
+        ledger.record(1_000_000 + HOUR, HOUR);
+        ledger
+    }
+
+    #[test]
+    fn test_new_game_has_no_offline_time() {
+        let mut ledger = SessionLedger::new();
+
+        let check = ledger.start_session(1_000_000);
+
+        assert_eq!(check, ClockCheck { time_elapsed: 0, anomaly: None });
+        assert_eq!(ledger.session_count, 1);
+        assert_eq!(ledger.sessions, vec![Session { start: 1_000_000, end: 1_000_000 }]);
+    }
+
+    #[test]
+    fn test_offline_time_and_play_time() {
+        let mut ledger = played_ledger();
+
+        let check = ledger.start_session(1_000_000 + 3 * HOUR);
+        ledger.record(1_000_000 + 4 * HOUR, HOUR);
+
+        assert_eq!(check.time_elapsed, 2 * HOUR);
+        assert_eq!(check.anomaly, None);
+        assert_eq!(ledger.play_time_secs, 2 * HOUR);
+        assert_eq!(ledger.last_seen, 1_000_000 + 4 * HOUR);
+        assert!(ledger.is_clock_trusted());
+    }
+
+    #[test]
+    fn test_clock_went_backwards() {
+        let mut ledger = played_ledger();
+
+        let check = ledger.start_session(1_000_000 - HOUR);
+
+        assert_eq!(check.time_elapsed, 0);
+        assert_eq!(check.anomaly.unwrap().kind, ClockAnomalyKind::WentBackwards);
+        assert_eq!(check.anomaly.unwrap().seconds, 2 * HOUR);
+        // winding the clock forward again does not grant the same time twice
+        ledger.record(1_000_000, HOUR);
+        assert_eq!(ledger.latest_seen, 1_000_000 + HOUR);
+        assert!(!ledger.is_clock_trusted());
+    }
+
+    #[test]
+    fn test_forward_jump_ignored_until_clock_is_trusted() {
+        let mut ledger = played_ledger();
+        ledger.start_session(1_000_000);
+
+        let check = ledger.start_session(1_000_000 + 24 * HOUR);
+        assert_eq!(check.time_elapsed, 0);
+        assert_eq!(check.anomaly.unwrap().kind, ClockAnomalyKind::ForwardJumpIgnored);
+
+        for session in 1..CLOCK_TRUST_SESSIONS {
+            let check = ledger.start_session(1_000_000 + (24 + session) * HOUR);
+            assert_eq!(check.time_elapsed, 0);
+        }
+        let check = ledger.start_session(1_000_000 + 48 * HOUR);
+        assert_eq!(check.time_elapsed, (24 - CLOCK_TRUST_SESSIONS + 1) * HOUR);
+        assert_eq!(check.anomaly, None);
+    }
+
+    #[test]
+    fn test_forward_jump_far_outside_the_history_is_clamped() {
+        let mut ledger = played_ledger();
+        // sessions an hour long, three hours apart
+        for session in 1..=CLOCK_GAP_HISTORY as u64 {
+            let start = 1_000_000 + 4 * session * HOUR;
+            ledger.start_session(start);
+            ledger.record(start + HOUR, HOUR);
+        }
+        let last_end = ledger.latest_seen;
+
+        let check = ledger.start_session(last_end + 24 * HOUR);
+
+        assert_eq!(check.time_elapsed, 3 * CLOCK_GAP_FACTOR * HOUR);
+        assert_eq!(check.anomaly.unwrap().kind, ClockAnomalyKind::ForwardJumpIgnored);
+        assert_eq!(check.anomaly.unwrap().seconds, (24 - 3 * CLOCK_GAP_FACTOR) * HOUR);
+        // a gap like the others is counted in full
+        ledger.record(last_end + 25 * HOUR, HOUR);
+        let check = ledger.start_session(last_end + 28 * HOUR);
+        assert_eq!(check, ClockCheck { time_elapsed: 3 * HOUR, anomaly: None });
+    }
+
+    #[test]
+    fn test_now_does_not_follow_the_clock() {
+        let mut ledger = played_ledger();
+        ledger.start_session(1_000_000 + 2 * HOUR);
+
+        ledger.checkpoint(1_000_000 + 48 * HOUR);
+        assert_eq!(ledger.now(), 1_000_000 + 2 * HOUR);
+
+        // a clock set back is still ahead of the latest time seen
+        let mut ledger = played_ledger();
+        ledger.start_session(1_000_000 - 24 * HOUR);
+        assert_eq!(ledger.now(), 1_000_000 + HOUR);
+    }
+
+    #[test]
+    fn test_clock_changed_during_session() {
+        let mut ledger = played_ledger();
+        ledger.start_session(1_000_000 + 24 * HOUR);
+
+        // the clock was moved a day back while playing for an hour
+        ledger.record(1_000_000 + HOUR, HOUR);
+        ledger.record(1_000_000 + 2 * HOUR, 2 * HOUR);
+
+        assert_eq!(ledger.anomalies.len(), 1);
+        assert_eq!(ledger.anomalies[0].kind, ClockAnomalyKind::ChangedDuringSession);
+        assert_eq!(ledger.anomalies[0].seconds, 24 * HOUR);
+        assert_eq!(ledger.last_seen, 1_000_000 + 26 * HOUR);
+        assert_eq!(ledger.play_time_secs, 3 * HOUR);
+        assert!(!ledger.is_clock_trusted());
+    }
+
+    #[test]
+    fn test_suspend_during_session_is_not_an_anomaly() {
+        let mut ledger = played_ledger();
+        ledger.start_session(1_000_000 + 2 * HOUR);
+
+        // the laptop slept for 8 hours with the game open, the monotonic clock only saw the hour
+        // that was played
+        ledger.record(1_000_000 + 11 * HOUR, HOUR);
+
+        assert!(ledger.anomalies.is_empty());
+        assert!(ledger.is_clock_trusted());
+        assert_eq!(ledger.play_time_secs, 2 * HOUR);
+
+        let check = ledger.start_session(1_000_000 + 12 * HOUR);
+        assert_eq!(check.anomaly, None);
+        assert!(check.time_elapsed > 0);
+    }
+}
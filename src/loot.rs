@@ -0,0 +1,139 @@
+// Loot tables roll extra items on top of the fixed items of an activity. Every roll picks one
+// entry by weight, or nothing, and a quantity in the range of the entry.
+
+use crate::constants::LOOT_SAMPLE_LIMIT;
+use crate::item::Item;
+use crate::job::Job;
+use crate::rng::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LootEntry {
+    pub item_id: u128,
+    pub weight: u32,
+    pub min_quantity: u128,
+    pub max_quantity: u128,
+    // job id and level, the entry is left out of the table until the job reaches it
+    pub required_job: Option<(u128, u8)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LootTable {
+    // rolls per cycle
+    pub rolls: u32,
+    // weight of rolling nothing
+    pub empty_weight: u32,
+    pub entries: Vec<LootEntry>,
+}
+
+impl LootEntry {
+    fn is_unlocked(&self, jobs: &[Job]) -> bool {
+        match self.required_job {
+            Some((job_id, level)) => jobs.iter().any(|job| job.id == job_id && job.level >= level),
+            None => true,
+        }
+    }
+}
+
+impl LootTable {
+    // Rolls the table for a number of cycles. Up to LOOT_SAMPLE_LIMIT rolls are sampled one by
+    // one, past that the entries get their expected share and only what is left is sampled.
+    pub fn roll(&self, cycles: u128, jobs: &[Job], rng: &mut Rng) -> Vec<Item> {
+        let entries: Vec<&LootEntry> = self.entries.iter().filter(|entry| entry.is_unlocked(jobs)).collect();
+        let total_weight =
+            u128::from(self.empty_weight) + entries.iter().map(|entry| u128::from(entry.weight)).sum::<u128>();
+        if total_weight == 0 || entries.is_empty() {
+            return vec![];
+        }
+
+        let mut rolls = cycles.saturating_mul(u128::from(self.rolls));
+        let mut quantities = vec![0u128; entries.len()];
+        if rolls > LOOT_SAMPLE_LIMIT {
+            let share = |weight: u32| rolls.saturating_mul(u128::from(weight)) / total_weight;
+            let mut left = rolls - share(self.empty_weight);
+            for (entry, quantity) in entries.iter().zip(quantities.iter_mut()) {
+                let picks = share(entry.weight);
+                let range = entry.max_quantity.saturating_sub(entry.min_quantity);
+                *quantity = picks.saturating_mul(entry.min_quantity).saturating_add(picks.saturating_mul(range) / 2);
+                left -= picks;
+            }
+            // only the rolls that did not divide evenly are sampled
+            rolls = left;
+        }
+
+        for _ in 0..rolls {
+            let mut pick = rng.range(0, total_weight - 1);
+            for (entry, quantity) in entries.iter().zip(quantities.iter_mut()) {
+                if pick < u128::from(entry.weight) {
+                    *quantity += rng.range(entry.min_quantity, entry.max_quantity);
+                    break;
+                }
+                pick -= u128::from(entry.weight);
+            }
+        }
+
+        entries
+            .iter()
+            .zip(quantities)
+            .filter(|(_, quantity)| *quantity > 0)
+            .map(|(entry, quantity)| Item::new(entry.item_id, quantity))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::mock::*;
+
+    fn mining_table() -> LootTable {
+        LootTable {
+            rolls: 1,
+            empty_weight: 50,
+            entries: vec![
+                LootEntry { item_id: 2, weight: 40, min_quantity: 1, max_quantity: 3, required_job: None },
+                LootEntry { item_id: 7, weight: 10, min_quantity: 1, max_quantity: 1, required_job: Some((MINER, 5)) },
+            ],
+        }
+    }
+
+    fn quantity(items: &[Item], id: u128) -> u128 {
+        items.iter().filter(|item| item.id == id).map(|item| item.quantity).sum()
+    }
+
+    #[test]
+    fn test_roll_is_reproducible() {
+        let table = mining_table();
+        let jobs = jobs();
+
+        let first = table.roll(100, &jobs, &mut Rng::new(1));
+        let second = table.roll(100, &jobs, &mut Rng::new(1));
+
+        assert_eq!(first, second);
+        // about 40 rolls of 2 stones on average
+        assert!((40..=120).contains(&quantity(&first, 2)));
+    }
+
+    #[test]
+    fn test_roll_gates_entries_by_job_level() {
+        let table = mining_table();
+        let mut jobs = jobs();
+
+        assert_eq!(quantity(&table.roll(1000, &jobs, &mut Rng::new(1)), 7), 0);
+        jobs[1].level = 5;
+        assert!(quantity(&table.roll(1000, &jobs, &mut Rng::new(1)), 7) > 0);
+    }
+
+    #[test]
+    fn test_roll_aggregates_long_absences() {
+        let table = mining_table();
+        let mut jobs = jobs();
+        jobs[1].level = 5;
+
+        let items = table.roll(1_000_000, &jobs, &mut Rng::new(1));
+
+        // 40% of the rolls give 2 stones on average, 10% give an ore
+        assert_eq!(quantity(&items, 2), 800_000);
+        assert_eq!(quantity(&items, 7), 100_000);
+    }
+}
@@ -1,29 +1,58 @@
 mod activity;
 mod constants;
+mod crafting;
+mod equipment;
 mod game_state;
+mod headless;
 mod inventory;
 mod item;
 mod job;
+mod ledger;
+mod loot;
+mod offline;
 mod player;
 mod save;
+mod shop;
+mod simulation;
+mod simulator;
+mod slots;
 mod ui;
 mod quest;
+mod rng;
 mod utils;
+mod xp_curve;
 
 use crate::activity::Activity;
-use crate::activity::ActivityName;
 use crate::game_state::GameState;
 use crate::game_state::QuestState;
-use crate::item::Item;
-use crate::job::JobName;
 use crate::ui::ButtonClicked;
+use crate::ui::UiState;
 use eframe::egui;
 use player::Player;
 use crate::utils::load_item_database;
-use crate::utils::ItemDatabase;
 use crate::utils::load_quest_database;
-use crate::utils::QuestDatabase;
+use crate::utils::load_activity_database;
+use crate::utils::load_recipe_database;
+use crate::utils::load_offline_config;
+use crate::utils::load_job_database;
+use crate::utils::load_xp_curve_database;
+use crate::utils::load_vendor_database;
+use crate::utils::Databases;
 use crate::quest::Quest;
+use crate::utils::current_timestamp;
+use crate::constants::LEGACY_SAVE_PATH;
+use crate::constants::LEGACY_SLOT_NAME;
+use crate::constants::SAVES_DIRECTORY;
+use crate::constants::SAVE_BACKUP_COUNT;
+use crate::constants::AUTOSAVE_INTERVAL_SECS;
+use crate::ledger::SessionLedger;
+use crate::save::Autosaver;
+use crate::simulation::Simulation;
+use crate::save::LoadFailure;
+use crate::save::SaveError;
+use crate::slots::SlotInfo;
+use crate::offline::OfflineReport;
+use crate::ui::SlotPickerState;
 
 fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
@@ -31,54 +60,207 @@ fn main() -> Result<(), eframe::Error> {
         ..Default::default()
     };
 
-    let save = save::load("save.json");
-    let mut game_state = GameState::new();
-    let mut player = Player::new();
-    let mut quests = vec![];
-    let mut time_elapsed = 0;
-
-    if save.is_some() {
-        game_state = save.clone().unwrap().0;
-        player = save.clone().unwrap().1;
-        quests = save.clone().unwrap().2;
-        let timestamp = save.clone().unwrap().3;
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        if current_time > timestamp {
-            time_elapsed = current_time - timestamp;
-        } else {
-            println!("What the heck, time went backwards?");
-            time_elapsed = 0;
-        }
-
-        println!("Time elapsed: {} seconds", time_elapsed);
+    match slots::import_legacy_save(SAVES_DIRECTORY, LEGACY_SAVE_PATH, LEGACY_SLOT_NAME, SAVE_BACKUP_COUNT) {
+        Ok(true) => println!("Imported {} into the save slot {}", LEGACY_SAVE_PATH, LEGACY_SLOT_NAME),
+        Ok(false) => {}
+        Err(e) => println!("Error importing {}: {}", LEGACY_SAVE_PATH, e),
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--headless") {
+        match headless::parse_args(&args).and_then(|options| headless::run(&options)) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+    if args.iter().any(|arg| arg == "--simulate") {
+        match simulator::parse_args(&args).and_then(|options| simulator::run(&options)) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
     }
 
     // Run the eframe application
     eframe::run_native(
         "Idle Game", // Window title
         options,
-        Box::new(move |_cc| Box::new(MyApp::new(player, game_state, quests, time_elapsed))), // Create and run our app
+        Box::new(move |_cc| Box::new(Launcher::new())), // Create and run our app
     )
 }
 
+// Loads the game from a save, falling back to its backups, or starts a new game
+fn load_game(save_name: &str) -> MyApp {
+    let mut game_state = GameState::new();
+    let mut player = Player::with_seed(current_timestamp());
+    let mut quests = vec![];
+    let mut ledger = SessionLedger::new();
+    let mut load_failure = None;
+
+    let loaded = match save::load(save_name) {
+        Ok(save) => Some(save),
+        Err(error) => {
+            let backup_name = match &error {
+                SaveError::Missing => None,
+                _ => {
+                    // keep a copy of the save before anything can overwrite it
+                    println!("Error loading save: {}", error);
+                    match save::backup_unreadable(save_name) {
+                        Ok(backup_name) => Some(backup_name),
+                        Err(e) => {
+                            println!("Error backing up save: {}", e);
+                            None
+                        }
+                    }
+                }
+            };
+
+            match save::load_newest_backup(save_name, SAVE_BACKUP_COUNT) {
+                Some((restored_from, save)) => {
+                    println!("Restored save from {}", restored_from);
+                    load_failure = Some(LoadFailure { error, backup_name, restored_from: Some(restored_from) });
+                    Some(save)
+                }
+                None if matches!(error, SaveError::Missing) => {
+                    println!("No save found, starting a new game");
+                    None
+                }
+                None => {
+                    load_failure = Some(LoadFailure { error, backup_name, restored_from: None });
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(save) = loaded {
+        game_state = save.game_state;
+        player = save.player;
+        quests = save.quests;
+        ledger = save.ledger;
+    }
+
+    MyApp::new(save_name.to_string(), player, game_state, quests, ledger, load_failure)
+}
+
+// Shows the save slot picker until a slot is opened, then runs the game in that slot
+struct Launcher {
+    slots: Vec<SlotInfo>,
+    slot_picker_state: SlotPickerState,
+    app: Option<MyApp>,
+}
+
+impl Launcher {
+    fn new() -> Self {
+        let mut launcher = Self {
+            slots: vec![],
+            slot_picker_state: SlotPickerState::new(),
+            app: None,
+        };
+        launcher.refresh_slots();
+        launcher
+    }
+
+    fn refresh_slots(&mut self) {
+        match slots::list_slots(SAVES_DIRECTORY) {
+            Ok(slots) => self.slots = slots,
+            Err(e) => self.slot_picker_state.error = Some(e.to_string()),
+        }
+    }
+
+    fn handle_slot_result(&mut self, result: Result<(), SaveError>) {
+        match result {
+            Ok(()) => {
+                self.slot_picker_state.error = None;
+                self.slot_picker_state.new_name.clear();
+            }
+            Err(e) => self.slot_picker_state.error = Some(e.to_string()),
+        }
+        self.refresh_slots();
+    }
+}
+
+impl eframe::App for Launcher {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if let Some(app) = &mut self.app {
+            app.update(ctx, frame);
+            return;
+        }
+
+        match ui::show_slot_picker(ctx, &self.slots, &mut self.slot_picker_state) {
+            Some(ButtonClicked::OpenSlot(name)) => match slots::open_slot_path(SAVES_DIRECTORY, &name) {
+                Ok(save_name) => self.app = Some(load_game(&save_name)),
+                Err(e) => self.slot_picker_state.error = Some(e.to_string()),
+            },
+            Some(ButtonClicked::CreateSlot(name)) => {
+                let result = slots::create_slot(SAVES_DIRECTORY, &name, SAVE_BACKUP_COUNT);
+                self.handle_slot_result(result);
+            }
+            Some(ButtonClicked::RenameSlot(name, new_name)) => {
+                let result = slots::rename_slot(SAVES_DIRECTORY, &name, &new_name, SAVE_BACKUP_COUNT);
+                // keep the old slot selected if the rename failed
+                if result.is_ok() {
+                    self.slot_picker_state.selected = Some(new_name);
+                }
+                self.handle_slot_result(result);
+            }
+            Some(ButtonClicked::DuplicateSlot(name, new_name)) => {
+                let result = slots::duplicate_slot(SAVES_DIRECTORY, &name, &new_name);
+                self.handle_slot_result(result);
+            }
+            Some(ButtonClicked::DeleteSlot(name)) => {
+                let result = slots::delete_slot(SAVES_DIRECTORY, &name, SAVE_BACKUP_COUNT);
+                self.slot_picker_state.selected = None;
+                self.handle_slot_result(result);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+        if let Some(app) = &mut self.app {
+            app.on_exit(gl);
+        }
+    }
+}
+
 // Struct to hold application state
 struct MyApp {
+    save_name: String,
     player: Player,
     game_state: GameState,
     quests: Vec<Quest>,
-    quest_database: QuestDatabase,
-    item_database: ItemDatabase,
+    // when the game was played, used to check the clock before granting offline progress
+    ledger: SessionLedger,
+    databases: Databases,
+    ui_state: UiState,
+    // set when the save could not be loaded, the game waits for the player to acknowledge it
+    load_failure: Option<LoadFailure>,
+    // saving is disabled when it could destroy a save we failed to load
+    save_enabled: bool,
+    autosaver: Autosaver,
+    last_update: std::time::Instant,
+    simulation: Simulation,
+    // shown once when the game starts if progress was made while it was closed
+    offline_report: Option<OfflineReport>,
 }
 
 impl MyApp {
-    fn new(mut player: Player, game_state: GameState, mut quests: Vec<Quest>, time_elapsed: u64) -> Self {
-        //get the player's current activity and update it based on the time elapsed
-        player.update_from_time_elapsed(time_elapsed);
-
+    fn new(
+        save_name: String,
+        mut player: Player,
+        game_state: GameState,
+        mut quests: Vec<Quest>,
+        mut ledger: SessionLedger,
+        load_failure: Option<LoadFailure>,
+    ) -> Self {
         let item_database = match load_item_database() {
             Ok(item_database) => item_database,
             Err(e) => {
@@ -95,6 +277,79 @@ impl MyApp {
             }
         };
 
+        let activity_database = match load_activity_database() {
+            Ok(activity_database) => activity_database,
+            Err(e) => {
+                println!("Error loading activity database: {}", e);
+                panic!("Failed to load activity database");
+            }
+        };
+
+        let recipe_database = match load_recipe_database() {
+            Ok(recipe_database) => recipe_database,
+            Err(e) => {
+                println!("Error loading recipe database: {}", e);
+                panic!("Failed to load recipe database");
+            }
+        };
+
+        let vendor_database = match load_vendor_database() {
+            Ok(vendor_database) => vendor_database,
+            Err(e) => {
+                println!("Error loading vendor database: {}", e);
+                panic!("Failed to load vendor database");
+            }
+        };
+
+        let job_database = match load_job_database() {
+            Ok(job_database) => job_database,
+            Err(e) => {
+                println!("Error loading job database: {}", e);
+                panic!("Failed to load job database");
+            }
+        };
+        let xp_curve_database = match load_xp_curve_database() {
+            Ok(xp_curve_database) => xp_curve_database,
+            Err(e) => {
+                println!("Error loading XP curve database: {}", e);
+                panic!("Failed to load XP curve database");
+            }
+        };
+        //add the jobs the save does not have yet and refresh the names, icons and XP curves
+        player.update_data(&job_database, &xp_curve_database);
+        player.inventory.update_item_data(&item_database);
+        player.update_activity_data(&activity_database);
+
+        let offline_config = match load_offline_config() {
+            Ok(offline_config) => offline_config,
+            Err(e) => {
+                println!("Error loading offline config: {}", e);
+                panic!("Failed to load offline config");
+            }
+        };
+
+        // the ledger decides how much of the time since the last session can be trusted
+        let clock_check = ledger.start_session(current_timestamp());
+        println!("Time elapsed: {} seconds", clock_check.time_elapsed);
+
+        //get the player's current activity and update it based on the time elapsed
+        let offline_report = match player.update_from_time_elapsed(clock_check.time_elapsed, &offline_config) {
+            Ok(mut report) => {
+                report.clock_anomaly = clock_check.anomaly;
+                if report.is_empty() {
+                    None
+                } else {
+                    Some(report)
+                }
+            }
+            Err(e) => {
+                println!("Error applying offline progress: {}", e);
+                None
+            }
+        };
+
+        let save_enabled = load_failure.is_none();
+
         //if the game state has no quests, that means that its a new save
         //so we need to load the quests from the quest database
         if quests.len() == 0 {
@@ -104,7 +359,14 @@ impl MyApp {
             }
         }
 
-        Self { player, game_state, item_database, quest_database, quests }
+        let databases = Databases {
+            items: item_database,
+            quests: quest_database,
+            activities: activity_database,
+            recipes: recipe_database,
+            vendors: vendor_database,
+        };
+        Self { save_name, player, game_state, databases, quests, ledger, ui_state: UiState::new(), load_failure, save_enabled, autosaver: Autosaver::new(std::time::Duration::from_secs(AUTOSAVE_INTERVAL_SECS)), last_update: std::time::Instant::now(), simulation: Simulation::new(), offline_report }
     }
 }
 
@@ -112,15 +374,51 @@ impl MyApp {
 impl eframe::App for MyApp {
     // This 'update' function is called on every frame
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let delta_time = ctx.input(|i| i.stable_dt);
+        // Nothing runs until the player has decided what to do about a save we could not load
+        if let Some(load_failure) = &self.load_failure {
+            match ui::show_load_failure_dialog(ctx, load_failure) {
+                Some(ButtonClicked::StartNewGame) | Some(ButtonClicked::ContinueFromBackup) => {
+                    self.save_enabled = load_failure.is_safe_to_overwrite();
+                    self.load_failure = None;
+                    self.last_update = std::time::Instant::now();
+                }
+                Some(ButtonClicked::QuitWithoutSaving) => {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Only whole milliseconds are consumed, the rest carries over to the next frame
+        let elapsed_ms = self.last_update.elapsed().as_millis() as u64;
+        self.last_update += std::time::Duration::from_millis(elapsed_ms);
 
-        // --- Game Logic using delta_time would go here ---
-        // e.g., self.player.passive_update(delta_time);
-        self.player.update(delta_time).unwrap();
+        // --- Game Logic using elapsed_ms goes here ---
+        let total_job_level = self.player.total_job_level();
+        match self.simulation.advance(&mut self.player, elapsed_ms) {
+            Ok(progress) => {
+                if progress.levels > 0 {
+                    println!("Player reached level {}", self.player.level);
+                }
+                if progress.levels > 0 || self.player.total_job_level() > total_job_level {
+                    self.autosaver.request();
+                }
+            }
+            Err(e) => println!("Error updating the game: {}", e),
+        }
 
         // --- Draw UI and get events ---
         // Call ui::update and capture the returned event
-        let ui_event = ui::update(&mut self.player, ctx, &self.game_state, &self.quests, &self.item_database, &self.quest_database);
+        let ui_event = ui::update(&mut self.player, ctx, &self.game_state, &self.quests, &self.databases, &mut self.ui_state, self.ledger.now());
+
+        if let Some(offline_report) = &self.offline_report {
+            if let Some(ButtonClicked::DismissOfflineReport) =
+                ui::show_offline_report_dialog(ctx, offline_report, &self.databases.items)
+            {
+                self.offline_report = None;
+            }
+        }
 
         // --- Handle events returned from UI ---
         if let Some(button_clicked) = ui_event {
@@ -134,32 +432,30 @@ impl eframe::App for MyApp {
                 ButtonClicked::Inventory => {
                     self.game_state = GameState::Inventory;
                 }
-                ButtonClicked::Mining => {
-                    self.player.set_activity(Activity::new(
-                        ActivityName::Mining,
-                        "Mining".to_string(),
-                        10.0,
-                        vec![(JobName::Miner, 100)],
-                        vec![Item::new(2, 1)],
-                    ));
-                }
-                ButtonClicked::Woodcutting => {
-                    self.player.set_activity(Activity::new(
-                        ActivityName::Woodcutting,
-                        "Woodcutting".to_string(),
-                        10.0,
-                        vec![(JobName::Woodcutter, 100)],
-                        vec![Item::new(1, 1)],
-                    ));
-                }
-                ButtonClicked::Farming => {
-                    self.player.set_activity(Activity::new(
-                        ActivityName::Farming,
-                        "Farming".to_string(),
-                        10.0,
-                        vec![(JobName::Farmer, 100)],
-                        vec![Item::new(3, 1)],
-                    ));
+                ButtonClicked::StartActivity(activity_id) => {
+                    match self.databases.activities.get(&activity_id) {
+                        Some(activity_data) => {
+                            self.player.set_activity(Activity::from_data(activity_data));
+                        }
+                        None => {
+                            println!("Activity not found");
+                        }
+                    }
+                }
+                ButtonClicked::Craft(recipe_id, quantity) => {
+                    match self.databases.recipes.get(&recipe_id) {
+                        Some(recipe_data) => {
+                            if let Err(e) = self.player.start_crafting(recipe_data, quantity) {
+                                println!("Could not start crafting: {}", e);
+                            }
+                        }
+                        None => {
+                            println!("Recipe not found");
+                        }
+                    }
+                }
+                ButtonClicked::CancelCrafting => {
+                    self.player.cancel_crafting();
                 }
                 ButtonClicked::Quest => {
                     self.game_state = GameState::Quest(QuestState::Available);
@@ -170,10 +466,56 @@ impl eframe::App for MyApp {
                 ButtonClicked::CompletedQuests => {
                     self.game_state = GameState::Quest(QuestState::Completed);
                 }
+                ButtonClicked::Prestige => {
+                    self.game_state = GameState::Prestige;
+                }
+                ButtonClicked::PrestigeJob(job_id) => match self.player.prestige_job(job_id) {
+                    Ok(()) => self.autosaver.request(),
+                    Err(e) => println!("Error prestiging: {}", e),
+                },
+                ButtonClicked::Equip(item_id) => match self.databases.items.get(&item_id) {
+                    Some(item_data) => {
+                        if let Err(e) = self.player.equip(item_data) {
+                            println!("Could not equip: {}", e);
+                        }
+                    }
+                    None => println!("Item not found"),
+                },
+                ButtonClicked::Unequip(slot) => {
+                    if let Err(e) = self.player.unequip(slot) {
+                        println!("Could not unequip: {}", e);
+                    }
+                }
+                ButtonClicked::Shop => {
+                    self.game_state = GameState::Shop;
+                }
+                ButtonClicked::Buy(vendor_id, item_id, quantity) => {
+                    match (self.databases.vendors.get(&vendor_id), self.databases.items.get(&item_id)) {
+                        (Some(vendor), Some(item_data)) => {
+                            match self.player.buy(vendor, item_data, quantity, self.ledger.now()) {
+                                Ok(()) => self.autosaver.request(),
+                                Err(e) => println!("Could not buy: {}", e),
+                            }
+                        }
+                        _ => println!("Vendor or item not found"),
+                    }
+                }
+                ButtonClicked::Sell(vendor_id, item_id, quantity) => {
+                    match (self.databases.vendors.get(&vendor_id), self.databases.items.get(&item_id)) {
+                        (Some(vendor), Some(item_data)) => match self.player.sell(vendor, item_data, quantity) {
+                            Ok(()) => self.autosaver.request(),
+                            Err(e) => println!("Could not sell: {}", e),
+                        },
+                        _ => println!("Vendor or item not found"),
+                    }
+                }
                 ButtonClicked::QuestCompleteClicked(quest_id) => {
                     match self.quests.iter_mut().find(|quest| quest.id == quest_id) {
                         Some(quest) => {
-                            quest.complete(&mut self.player, &self.quest_database);
+                            quest.complete(&mut self.player, &self.databases.quests);
+                            if quest.completed {
+                                self.autosaver.request();
+                            }
                         }
                         None => {
                             println!("Quest not found");
@@ -186,11 +528,23 @@ impl eframe::App for MyApp {
         }
 
         // Add handling for other potential events from the UI here later...
+
+        if self.save_enabled {
+            self.autosaver.update(&self.game_state, &self.player, &self.quests, &mut self.ledger, &self.save_name, SAVE_BACKUP_COUNT);
+        }
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.autosaver.wait();
+        if !self.save_enabled {
+            println!("Exiting application without saving.");
+            return;
+        }
         println!("Exiting application. saving...");
-        save::save(&self.game_state, &self.player, &self.quests, "save.json");
-        println!("Save finished.");
+        self.ledger.checkpoint(current_timestamp());
+        match save::save(&self.game_state, &self.player, &self.quests, &self.ledger, &self.save_name, SAVE_BACKUP_COUNT) {
+            Ok(()) => println!("Save finished."),
+            Err(e) => println!("Error saving: {}", e),
+        }
     }
 }
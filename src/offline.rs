@@ -0,0 +1,315 @@
+// What happened while the game was closed
+
+use crate::item::Item;
+use crate::ledger::ClockAnomaly;
+use crate::player::Player;
+use crate::simulation::Progress;
+use crate::utils::format_items;
+use crate::utils::ItemDatabase;
+use serde::{Deserialize, Serialize};
+
+// Offline time up to `until_seconds` counts at `percent` of real time
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct EfficiencyTier {
+    pub until_seconds: u64,
+    pub percent: u64,
+}
+
+// Loaded from offline.json so offline balance can be tuned without a rebuild
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OfflineConfig {
+    pub max_seconds: u64,
+    // Sorted by until_seconds, time past the last tier uses its percent
+    pub efficiency: Vec<EfficiencyTier>,
+}
+
+impl OfflineConfig {
+    // No cap and full efficiency, for time that is simulated on purpose
+    pub fn unlimited() -> Self {
+        Self { max_seconds: u64::MAX, efficiency: vec![] }
+    }
+
+    pub fn capped_seconds(&self, time_elapsed: u64) -> u64 {
+        time_elapsed.min(self.max_seconds)
+    }
+
+    // Milliseconds of progress granted for being away for time_elapsed seconds
+    pub fn effective_ms(&self, time_elapsed: u64) -> u64 {
+        let capped = self.capped_seconds(time_elapsed) as u128;
+        let mut effective: u128 = 0;
+        let mut start: u128 = 0;
+        let mut percent: u128 = 100;
+        for tier in &self.efficiency {
+            percent = tier.percent as u128;
+            let end = (tier.until_seconds as u128).min(capped);
+            if end > start {
+                effective += (end - start) * 1000 * percent / 100;
+                start = end;
+            }
+        }
+        if capped > start {
+            effective += (capped - start) * 1000 * percent / 100;
+        }
+        effective.min(u64::MAX as u128) as u64
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct OfflineReport {
+    pub time_elapsed: u64,
+    // seconds left after the offline cap and the progress they were worth
+    pub time_capped: u64,
+    pub effective_ms: u64,
+    pub activity: Option<String>,
+    pub cycles: u128,
+    pub crafting: Option<String>,
+    pub crafts: u128,
+    // by job name
+    pub experience: Vec<(String, u128)>,
+    pub levels_gained: Vec<(String, u8)>,
+    pub player_levels_gained: u8,
+    pub player_level: u8,
+    pub items_gained: Vec<Item>,
+    pub items_used: Vec<Item>,
+    // set when the system clock looked tampered with
+    pub clock_anomaly: Option<ClockAnomaly>,
+}
+
+impl OfflineReport {
+    // Compares the player before and after the offline progress was applied
+    pub fn new(
+        time_elapsed: u64,
+        config: &OfflineConfig,
+        progress: Progress,
+        before: &Player,
+        after: &Player,
+    ) -> Self {
+        let mut experience = vec![];
+        let mut levels_gained = vec![];
+        for job in &after.jobs {
+            let previous = match before.get_job(job.id) {
+                Some(previous) => previous,
+                None => continue,
+            };
+            let gained = job.total_experience().saturating_sub(previous.total_experience());
+            if gained > 0 {
+                experience.push((job.name.clone(), gained));
+            }
+            if job.level > previous.level {
+                levels_gained.push((job.name.clone(), job.level - previous.level));
+            }
+        }
+
+        let mut item_ids: Vec<u128> = before
+            .inventory
+            .items
+            .keys()
+            .chain(after.inventory.items.keys())
+            .copied()
+            .collect();
+        item_ids.sort();
+        item_ids.dedup();
+
+        let mut items_gained = vec![];
+        let mut items_used = vec![];
+        for id in item_ids {
+            let previous = before.inventory.get_item_quantity(id);
+            let current = after.inventory.get_item_quantity(id);
+            if current > previous {
+                items_gained.push(Item::new(id, current - previous));
+            } else if previous > current {
+                items_used.push(Item::new(id, previous - current));
+            }
+        }
+
+        Self {
+            time_elapsed,
+            time_capped: config.capped_seconds(time_elapsed),
+            effective_ms: config.effective_ms(time_elapsed),
+            activity: before.current_activity.as_ref().map(|activity| activity.name.clone()),
+            cycles: progress.cycles,
+            crafting: before.current_crafting.as_ref().map(|crafting| crafting.name.clone()),
+            crafts: progress.crafts,
+            experience,
+            levels_gained,
+            player_levels_gained: after.level.saturating_sub(before.level),
+            player_level: after.level,
+            items_gained,
+            items_used,
+            clock_anomaly: None,
+        }
+    }
+
+    // True if nothing was gained, in which case there is nothing to tell the player
+    pub fn is_empty(&self) -> bool {
+        self.cycles == 0
+            && self.crafts == 0
+            && self.experience.is_empty()
+            && self.items_gained.is_empty()
+            && self.clock_anomaly.is_none()
+    }
+
+    // The report as plain text, one line per entry
+    pub fn summary(&self, item_database: &ItemDatabase) -> String {
+        let mut lines = vec![];
+        if let Some(activity) = &self.activity {
+            lines.push(format!("{}: {} cycles completed", activity, self.cycles));
+        }
+        if let Some(crafting) = &self.crafting {
+            lines.push(format!("Crafting {}: {} crafted", crafting, self.crafts));
+        }
+        for (job, experience) in &self.experience {
+            let levels = self
+                .levels_gained
+                .iter()
+                .find(|(leveled_job, _)| leveled_job == job)
+                .map_or(String::new(), |(_, levels)| {
+                    format!(" (+{} level{})", levels, if *levels == 1 { "" } else { "s" })
+                });
+            lines.push(format!("{}: +{} XP{}", job, experience, levels));
+        }
+        if self.player_levels_gained > 0 {
+            lines.push(self.player_level_line());
+        }
+        if !self.items_gained.is_empty() {
+            lines.push(format!("Items gained: {}", format_items(&self.items_gained, item_database)));
+        }
+        if !self.items_used.is_empty() {
+            lines.push(format!("Items used: {}", format_items(&self.items_used, item_database)));
+        }
+        lines.join("\n")
+    }
+
+    pub fn player_level_line(&self) -> String {
+        let levels = self.player_levels_gained;
+        format!(
+            "Player: +{} level{} (now level {})",
+            levels,
+            if levels == 1 { "" } else { "s" },
+            self.player_level
+        )
+    }
+
+    pub fn was_capped(&self) -> bool {
+        self.time_capped < self.time_elapsed
+    }
+
+    // Overall share of the capped time that counted, for display
+    pub fn efficiency_percent(&self) -> u64 {
+        if self.time_capped == 0 {
+            return 100;
+        }
+        (self.effective_ms as u128 * 100 / (self.time_capped as u128 * 1000)) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+    use crate::activity::Activity;
+    use crate::crafting::RecipeData;
+
+    fn tapering_config() -> OfflineConfig {
+        OfflineConfig {
+            max_seconds: 10 * 3600,
+            efficiency: vec![
+                EfficiencyTier { until_seconds: 2 * 3600, percent: 100 },
+                EfficiencyTier { until_seconds: 6 * 3600, percent: 50 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_offline_report() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.add_item(&Item::new(1, 3));
+        player.set_activity(Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cut down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 100)],
+            vec![Item::new(1, 1)],
+        ));
+        let recipe = RecipeData {
+            id: 1,
+            name: "Plank".to_string(),
+            description: "Saw a log into planks".to_string(),
+            inputs: vec![Item::new(1, 2)],
+            outputs: vec![Item::new(4, 1)],
+            job: WOODCUTTER,
+            required_level: 1,
+            duration_ms: 30_000,
+            experience: 10,
+        };
+        player.start_crafting(&recipe, 1).unwrap();
+
+        let report = player.update_from_time_elapsed(60, &OfflineConfig::unlimited()).unwrap();
+
+        // 6 logs cut, 2 used by the plank before going offline
+        assert_eq!(report.time_elapsed, 60);
+        assert_eq!(report.effective_ms, 60_000);
+        assert!(!report.was_capped());
+        assert_eq!(report.activity, Some("Woodcutting".to_string()));
+        assert_eq!(report.cycles, 6);
+        assert_eq!(report.crafting, Some("Plank".to_string()));
+        assert_eq!(report.crafts, 1);
+        assert_eq!(report.experience, vec![("Woodcutter".to_string(), 610)]);
+        assert_eq!(report.levels_gained, vec![("Woodcutter".to_string(), 3)]);
+        assert_eq!(report.items_gained, vec![Item::new(1, 6), Item::new(4, 1)]);
+        assert!(report.items_used.is_empty());
+        assert!(!report.is_empty());
+    }
+
+    #[test]
+    fn test_offline_report_without_activity_is_empty() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+
+        let report = player.update_from_time_elapsed(3600, &OfflineConfig::unlimited()).unwrap();
+
+        assert_eq!(report.activity, None);
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_effective_ms() {
+        let config = tapering_config();
+
+        assert_eq!(config.effective_ms(0), 0);
+        assert_eq!(config.effective_ms(3600), 3600 * 1000);
+        // 2h at 100%, then 2h at 50%
+        assert_eq!(config.effective_ms(4 * 3600), 3 * 3600 * 1000);
+        // past the last tier keeps its 50%, then the 10h cap kicks in
+        assert_eq!(config.effective_ms(8 * 3600), 5 * 3600 * 1000);
+        assert_eq!(config.effective_ms(10 * 3600), 6 * 3600 * 1000);
+        assert_eq!(config.effective_ms(1000 * 3600), 6 * 3600 * 1000);
+        assert_eq!(OfflineConfig::unlimited().effective_ms(1000 * 3600), 1000 * 3600 * 1000);
+    }
+
+    #[test]
+    fn test_offline_report_capped() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.set_activity(Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cut down trees".to_string(),
+            3_600_000,
+            vec![(WOODCUTTER, 1)],
+            vec![Item::new(1, 1)],
+        ));
+
+        let report = player.update_from_time_elapsed(48 * 3600, &tapering_config()).unwrap();
+
+        assert_eq!(report.time_elapsed, 48 * 3600);
+        assert_eq!(report.time_capped, 10 * 3600);
+        assert!(report.was_capped());
+        assert_eq!(report.efficiency_percent(), 60);
+        assert_eq!(report.cycles, 6);
+        assert_eq!(report.items_gained, vec![Item::new(1, 6)]);
+    }
+}
@@ -1,83 +1,221 @@
 use crate::activity::Activity;
-use crate::constants::LEVEL_UP_EXPERIENCE;
+use crate::constants::{
+    ATTACK_POWER_PER_LEVEL, DEFENSE_PER_LEVEL, HEALTH_PER_LEVEL, MANA_PER_LEVEL, PLAYER_BASE_ATTACK_POWER,
+    PLAYER_BASE_DEFENSE, PLAYER_BASE_HEALTH, PLAYER_BASE_MANA, PLAYER_DEFAULT_SEED, PLAYER_XP_CURVE,
+};
+use crate::crafting::Crafting;
+use crate::crafting::RecipeData;
+use crate::equipment::{Equipment, EquipmentSlot};
+use crate::offline::{OfflineConfig, OfflineReport};
+use crate::simulation::{Progress, Simulation};
 use crate::inventory::Inventory;
 use crate::job::Job;
-use crate::job::JobName;
+use crate::job::JobData;
 use crate::item::Item;
+use crate::item::ItemData;
+use crate::rng::Rng;
+use crate::shop::{Shop, VendorData};
+use crate::utils::ActivityDatabase;
+use crate::utils::JobDatabase;
+use crate::utils::XpCurveDatabase;
+use crate::xp_curve::XpCurve;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Player {
-    pub health: u8,
-    pub mana: u8,
-    pub attack_power: u8,
-    pub defense: u8,
+    pub health: u32,
+    pub mana: u32,
+    pub attack_power: u32,
+    pub defense: u32,
+    // levels up with the experience of every job, see update_level
     pub level: u8,
     pub jobs: Vec<Job>,
     pub inventory: Inventory,
     pub current_activity: Option<Activity>,
+    pub current_crafting: Option<Crafting>,
+    // at most one item per slot, taken out of the inventory while it is equipped
+    pub equipment: Vec<Equipment>,
+    pub shop: Shop,
+    // rolls the loot, saved so that a save always rolls the same loot
+    pub rng: Rng,
+    // looked up from PLAYER_XP_CURVE when the data is updated
+    #[serde(skip)]
+    pub xp_curve: XpCurve,
 }
 
 impl Player {
+    // The jobs are added from the job database with update_data. New games are seeded with
+    // with_seed, this seed only keeps tests reproducible.
     pub fn new() -> Self {
-        let jobs = vec![
-            Job::new(
-                JobName::Woodcutter,
-                "Cut down trees".to_string(),
-                0,
-                1,
-                LEVEL_UP_EXPERIENCE.to_vec(),
-            ),
-            Job::new(
-                JobName::Miner,
-                "Mine rocks".to_string(),
-                0,
-                1,
-                LEVEL_UP_EXPERIENCE.to_vec(),
-            ),
-            Job::new(
-                JobName::Farmer,
-                "Grow crops".to_string(),
-                0,
-                1,
-                LEVEL_UP_EXPERIENCE.to_vec(),
-            ),
-        ];
         Self {
-            health: 100,
-            mana: 100,
-            attack_power: 1,
-            defense: 1,
+            health: PLAYER_BASE_HEALTH,
+            mana: PLAYER_BASE_MANA,
+            attack_power: PLAYER_BASE_ATTACK_POWER,
+            defense: PLAYER_BASE_DEFENSE,
             level: 1,
-            jobs: jobs,
+            jobs: vec![],
             inventory: Inventory::new(),
             current_activity: None,
+            current_crafting: None,
+            equipment: vec![],
+            shop: Shop::new(),
+            rng: Rng::new(PLAYER_DEFAULT_SEED),
+            xp_curve: XpCurve::default(),
         }
     }
 
-    pub fn update(&mut self, delta_time: f32) -> Result<(), String> {
-        // Update player stats based on current occupation
-        match &mut self.current_activity {
-            Some(activity) => {
-                activity.update(delta_time, &mut self.jobs, &mut self.inventory)?;
+    pub fn with_seed(seed: u64) -> Self {
+        Self { rng: Rng::new(seed), ..Self::new() }
+    }
+
+    // Adds the jobs that are new in the job database, refreshes the names and XP curves of
+    // the others and catches the player level up with them
+    pub fn update_data(&mut self, job_database: &JobDatabase, xp_curve_database: &XpCurveDatabase) {
+        let find_curve = |id: u128| match xp_curve_database.get(&id) {
+            Some(curve) => curve.clone(),
+            None => {
+                println!("XP curve not found: {}", id);
+                XpCurve::default()
+            }
+        };
+        let curve = |job_data: &JobData| find_curve(job_data.xp_curve);
+        for job in &mut self.jobs {
+            if let Some(job_data) = job_database.get(&job.id) {
+                job.update_data(job_data, &curve(job_data));
+            }
+        }
+        for job_data in job_database.values() {
+            if self.get_job(job_data.id).is_none() {
+                self.jobs.push(Job::from_data(job_data, &curve(job_data)));
+            }
+        }
+        self.jobs.sort_by_key(|job| job.id);
+        self.xp_curve = find_curve(PLAYER_XP_CURVE);
+        self.update_level();
+    }
+
+    // Reattaches the loot table of the current activity, see Activity::update_data
+    pub fn update_activity_data(&mut self, activity_database: &ActivityDatabase) {
+        if let Some(activity) = &mut self.current_activity {
+            match activity_database.get(&activity.id) {
+                Some(activity_data) => activity.update_data(activity_data),
+                None => println!("Activity not found: {}", activity.id),
+            }
+        }
+    }
+
+    // The player experience is the experience of every job added up, including what was
+    // given up for prestige
+    pub fn experience(&self) -> u128 {
+        self.jobs.iter().map(|job| job.total_experience() + job.prestige_experience).sum()
+    }
+
+    // None at the max level
+    pub fn get_xp_needed_for_next_level(&self) -> Option<u128> {
+        let next_level = self.xp_curve.experience_to_next(self.level)?;
+        let experience = self.experience().saturating_sub(self.xp_curve.experience_to_reach(self.level));
+        Some(next_level.saturating_sub(experience))
+    }
+
+    // Levels up as far as the experience goes and grows the stats with it. Returns the number
+    // of levels gained.
+    pub fn update_level(&mut self) -> u8 {
+        let experience = self.experience();
+        let mut levels_gained = 0;
+        while let Some(needed) = self.xp_curve.experience_to_next(self.level) {
+            if experience < self.xp_curve.experience_to_reach(self.level) + needed {
+                break;
             }
-            None => {}
+            self.level += 1;
+            levels_gained += 1;
         }
+        if levels_gained > 0 {
+            self.update_stats();
+        }
+        levels_gained
+    }
+
+    // The stats of the level plus what the equipment adds
+    fn update_stats(&mut self) {
+        let levels = u32::from(self.level - 1);
+        self.health = PLAYER_BASE_HEALTH + HEALTH_PER_LEVEL * levels;
+        self.mana = PLAYER_BASE_MANA + MANA_PER_LEVEL * levels;
+        self.attack_power = PLAYER_BASE_ATTACK_POWER
+            + ATTACK_POWER_PER_LEVEL * levels
+            + self.equipment.iter().map(Equipment::attack_power).sum::<u32>();
+        self.defense = PLAYER_BASE_DEFENSE
+            + DEFENSE_PER_LEVEL * levels
+            + self.equipment.iter().map(Equipment::defense).sum::<u32>();
+    }
+
+    // Takes the item out of the inventory and puts back the one it replaces, or does nothing
+    // if there is no room for the one it replaces
+    pub fn equip(&mut self, item_data: &ItemData) -> Result<(), String> {
+        let equipment = Equipment::from_data(item_data)?;
+        self.inventory.remove_item(Item::new(item_data.id, 1))?;
+        if let Err(e) = self.unequip(equipment.slot) {
+            self.inventory.return_item(&Item::new(item_data.id, 1));
+            return Err(e);
+        }
+        self.equipment.push(equipment);
+        self.update_stats();
         Ok(())
     }
 
-    pub fn update_from_time_elapsed(&mut self, time_elapsed: u64) -> Result<(), String> {
-        match &mut self.current_activity {
-            Some(activity) => activity.update_from_time_elapsed(
-                time_elapsed,
-                &mut self.jobs,
-                &mut self.inventory,
-            )?,
-            None => {}
+    pub fn unequip(&mut self, slot: EquipmentSlot) -> Result<(), String> {
+        if let Some(index) = self.equipment.iter().position(|equipment| equipment.slot == slot) {
+            if self.inventory.add_item(&Item::new(self.equipment[index].item_id, 1)) > 0 {
+                return Err(format!("No room for {}", self.equipment[index].name));
+            }
+            self.equipment.remove(index);
+            self.update_stats();
         }
         Ok(())
     }
 
+    // now is a timestamp in seconds, for the restock timers
+    pub fn buy(&mut self, vendor: &VendorData, item_data: &ItemData, quantity: u128, now: u64) -> Result<(), String> {
+        self.shop.buy(vendor, item_data, quantity, &mut self.inventory, now)
+    }
+
+    pub fn sell(&mut self, vendor: &VendorData, item_data: &ItemData, quantity: u128) -> Result<(), String> {
+        Shop::sell(vendor, item_data, quantity, &mut self.inventory)
+    }
+
+    pub fn get_equipment(&self, slot: EquipmentSlot) -> Option<&Equipment> {
+        self.equipment.iter().find(|equipment| equipment.slot == slot)
+    }
+
+    // Advances the activity, then the crafting. Use the simulation rather than calling this
+    // directly, so that progress does not depend on how time is split up.
+    pub fn update(&mut self, elapsed_ms: u64) -> Result<Progress, String> {
+        let mut progress = Progress::default();
+        if let Some(activity) = &mut self.current_activity {
+            progress.cycles = activity.update(elapsed_ms, &mut self.jobs, &self.equipment, &mut self.inventory, &mut self.rng)?;
+        }
+        if let Some(crafting) = &mut self.current_crafting {
+            progress.crafts = crafting.update(elapsed_ms, &mut self.jobs, &mut self.inventory)?;
+            if crafting.is_finished() {
+                self.current_crafting = None;
+            }
+        }
+        if progress.cycles > 0 || progress.crafts > 0 {
+            progress.levels = self.update_level();
+        }
+        Ok(progress)
+    }
+
+    // Applies offline progress, limited by the offline cap and efficiency curve
+    pub fn update_from_time_elapsed(
+        &mut self,
+        time_elapsed: u64,
+        config: &OfflineConfig,
+    ) -> Result<OfflineReport, String> {
+        let before = self.clone();
+        let progress = Simulation::new().advance(self, config.effective_ms(time_elapsed))?;
+        Ok(OfflineReport::new(time_elapsed, config, progress, &before, self))
+    }
+
     pub fn set_activity(&mut self, activity: Activity) {
         self.current_activity = Some(activity);
     }
@@ -86,25 +224,64 @@ impl Player {
         self.current_activity.as_ref()
     }
 
+    // Starting a new craft cancels the one in progress and refunds its inputs
+    pub fn start_crafting(&mut self, recipe_data: &RecipeData, quantity: u32) -> Result<(), String> {
+        self.cancel_crafting();
+        let crafting = Crafting::start(recipe_data, quantity, &self.jobs, &mut self.inventory)?;
+        self.current_crafting = Some(crafting);
+        Ok(())
+    }
+
+    pub fn cancel_crafting(&mut self) {
+        if let Some(mut crafting) = self.current_crafting.take() {
+            crafting.cancel(&mut self.inventory);
+        }
+    }
+
+    pub fn get_crafting(&self) -> Option<&Crafting> {
+        self.current_crafting.as_ref()
+    }
+
     pub fn get_jobs(&self) -> &Vec<Job> {
         &self.jobs
     }
 
-    pub fn get_job(&self, job_name: JobName) -> Option<&Job> {
-        self.jobs.iter().find(|job| job.name == job_name)
+    pub fn get_job(&self, job_id: u128) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == job_id)
+    }
+
+    pub fn job_name(&self, job_id: u128) -> &str {
+        self.get_job(job_id).map_or("Unknown Job", |job| job.name.as_str())
+    }
+
+    pub fn total_job_level(&self) -> u32 {
+        self.jobs.iter().map(|job| u32::from(job.level)).sum()
     }
 
     pub fn get_inventory(&self) -> &Inventory {
         &self.inventory
     }
 
-    pub fn add_experience(&mut self, job_name: JobName, experience: u128) {
-        let job = self.jobs.iter_mut().find(|job| job.name == job_name).unwrap();
-        job.add_experience(experience);
+    pub fn add_experience(&mut self, job_id: u128, experience: u128) {
+        match self.jobs.iter_mut().find(|job| job.id == job_id) {
+            Some(job) => job.add_experience(experience),
+            None => println!("Job not found: {}", job_id),
+        }
+        self.update_level();
+    }
+
+    pub fn prestige_job(&mut self, job_id: u128) -> Result<(), String> {
+        match self.jobs.iter_mut().find(|job| job.id == job_id) {
+            Some(job) => job.prestige(),
+            None => Err(format!("Job not found: {}", job_id)),
+        }
     }
 
     pub fn add_item(&mut self, item: &Item) {
-        self.inventory.add_item(item);
+        let overflow = self.inventory.add_item(item);
+        if overflow > 0 {
+            println!("No room for {} of item {}", overflow, item.id);
+        }
     }
 
     pub fn add_gold(&mut self, amount: u128) {
@@ -115,3 +292,135 @@ impl Player {
         self.inventory.remove_gold(amount)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::PRESTIGE_MIN_LEVEL;
+    use crate::equipment::mock::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+
+    #[test]
+    fn test_player_levels_up_with_job_experience() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        assert_eq!(player.get_xp_needed_for_next_level(), Some(220));
+
+        player.add_experience(WOODCUTTER, 200);
+        player.add_experience(FARMER, 400);
+
+        assert_eq!(player.experience(), 600);
+        assert_eq!(player.level, 3);
+        assert_eq!(player.get_xp_needed_for_next_level(), Some(588));
+        assert_eq!(player.health, PLAYER_BASE_HEALTH + 2 * HEALTH_PER_LEVEL);
+        assert_eq!(player.mana, PLAYER_BASE_MANA + 2 * MANA_PER_LEVEL);
+        assert_eq!(player.attack_power, PLAYER_BASE_ATTACK_POWER + 2 * ATTACK_POWER_PER_LEVEL);
+        assert_eq!(player.defense, PLAYER_BASE_DEFENSE + 2 * DEFENSE_PER_LEVEL);
+    }
+
+    #[test]
+    fn test_player_level_catches_up_on_load() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.jobs[0].level = 4;
+        player.level = 1;
+
+        // what a save from before player levels looks like once it is loaded
+        player.update_data(&job_database(), &xp_curve_database());
+
+        assert_eq!(player.experience(), 110 + 170 + 314);
+        assert_eq!(player.level, 3);
+    }
+
+    #[test]
+    fn test_prestige_keeps_the_player_level() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        assert!(player.prestige_job(WOODCUTTER).is_err());
+
+        player.jobs[0].level = PRESTIGE_MIN_LEVEL;
+        player.update_level();
+        let (experience, level) = (player.experience(), player.level);
+        player.prestige_job(WOODCUTTER).unwrap();
+        player.add_experience(WOODCUTTER, 1);
+
+        assert_eq!(player.get_job(WOODCUTTER).unwrap().level, 1);
+        assert_eq!(player.experience(), experience + 1);
+        assert_eq!(player.level, level);
+    }
+
+    #[test]
+    fn test_player_update_reports_levels() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.set_activity(Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cut down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 220)],
+            vec![Item::new(1, 1)],
+        ));
+
+        let progress = player.update(10_000).unwrap();
+
+        assert_eq!(progress, Progress { cycles: 1, crafts: 0, levels: 1 });
+    }
+
+    #[test]
+    fn test_equip_swaps_items_and_stats() {
+        let mut player = Player::new();
+        player.add_item(&Item::new(COPPER_ARMOR, 1));
+        assert!(player.equip(&copper_axe()).is_err());
+
+        player.equip(&copper_armor()).unwrap();
+
+        assert_eq!(player.inventory.get_item_quantity(COPPER_ARMOR), 0);
+        assert_eq!(player.get_equipment(EquipmentSlot::Armor).unwrap().item_id, COPPER_ARMOR);
+        assert_eq!(player.defense, PLAYER_BASE_DEFENSE + 5);
+        assert_eq!(player.attack_power, PLAYER_BASE_ATTACK_POWER + 1);
+
+        player.unequip(EquipmentSlot::Armor).unwrap();
+
+        assert_eq!(player.inventory.get_item_quantity(COPPER_ARMOR), 1);
+        assert!(player.equipment.is_empty());
+        assert_eq!(player.defense, PLAYER_BASE_DEFENSE);
+    }
+
+    #[test]
+    fn test_unequip_needs_room() {
+        let mut player = Player::new();
+        player.inventory.stack_limits.insert(COPPER_ARMOR, 2);
+        player.add_item(&Item::new(COPPER_ARMOR, 2));
+        player.equip(&copper_armor()).unwrap();
+        player.add_item(&Item::new(COPPER_ARMOR, 1));
+
+        assert!(player.unequip(EquipmentSlot::Armor).is_err());
+        assert_eq!(player.get_equipment(EquipmentSlot::Armor).unwrap().item_id, COPPER_ARMOR);
+        assert_eq!(player.inventory.get_item_quantity(COPPER_ARMOR), 2);
+        assert_eq!(player.defense, PLAYER_BASE_DEFENSE + 5);
+    }
+
+    #[test]
+    fn test_equipment_speeds_up_activities() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.add_item(&Item::new(COPPER_AXE, 1));
+        player.equip(&copper_axe()).unwrap();
+        player.set_activity(Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cut down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 0)],
+            vec![Item::new(1, 1)],
+        ));
+
+        // 20% faster is 5 cycles of 8 seconds, with a 50% yield bonus
+        let progress = player.update(40_000).unwrap();
+
+        assert_eq!(progress.cycles, 5);
+        assert_eq!(player.inventory.get_item_quantity(1), 7);
+    }
+}
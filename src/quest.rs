@@ -1,6 +1,5 @@
 use crate::item::Item;
 use crate::player::Player;
-use crate::job::JobName;
 use serde::{Deserialize, Serialize};
 use crate::utils::QuestDatabase;
 
@@ -29,13 +28,13 @@ pub struct Goal {
 pub enum Objective {
     CollectItem(u128),
     CollectGold(),
-    ReachJobLevel(JobName),
+    ReachJobLevel(u128),
     ReachLevel(),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct JobExperience {
-    pub job: JobName,
+    pub job: u128,
     pub amount: u128,
 }
 
@@ -59,8 +58,8 @@ impl Quest {
             Objective::CollectGold() => {
                 player.inventory.gold >= quest_data.goal.required_amount
             }
-            Objective::ReachJobLevel(job_name) => {
-                match player.get_job(job_name.clone()) {
+            Objective::ReachJobLevel(job_id) => {
+                match player.get_job(*job_id) {
                     Some(job) => u128::from(job.level) >= quest_data.goal.required_amount,
                     None => false,
                 }
@@ -87,7 +86,7 @@ impl Quest {
         }
 
         if let Some(experience) = &quest_data.reward.experience {
-            player.add_experience(experience.job.clone(), experience.amount);
+            player.add_experience(experience.job, experience.amount);
         }
         if let Some(items) = &quest_data.reward.items {
             for item in items {
@@ -110,8 +109,11 @@ mod mock {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
     use mock::*;
 
     #[test]
@@ -119,7 +121,7 @@ mod tests {
         let quest_id = 1;
         let quest_name = "Test Quest".to_string();
         let quest_description = "Test Description".to_string();
-        let quest_reward = Reward { experience: Some(JobExperience { job: JobName::Woodcutter, amount: 100 }), items: None, gold: Some(100) };
+        let quest_reward = Reward { experience: Some(JobExperience { job: WOODCUTTER, amount: 100 }), items: None, gold: Some(100) };
         let quest_goal = Goal { objective: Objective::CollectItem(1), required_amount: 10 };
 
         let quest_data = QuestData { id: quest_id, name: quest_name, description: quest_description, reward: quest_reward, goal: quest_goal };
@@ -134,12 +136,13 @@ mod tests {
         let quest_id = 1;
         let quest_name = "Test Quest".to_string();
         let quest_description = "Test Description".to_string();
-        let quest_reward = Reward { experience: Some(JobExperience { job: JobName::Woodcutter, amount: 100 }), items: None, gold: Some(100) };
+        let quest_reward = Reward { experience: Some(JobExperience { job: WOODCUTTER, amount: 100 }), items: None, gold: Some(100) };
         let quest_goal = Goal { objective: Objective::CollectItem(1), required_amount: 1 };
 
         let quest_data = QuestData { id: quest_id, name: quest_name, description: quest_description, reward: quest_reward, goal: quest_goal };
         let quest = Quest::new(quest_id, false);
         let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
 
         assert_eq!(quest.check_completion(&quest_data, &player), false);
 
@@ -154,12 +157,13 @@ mod tests {
         let quest_id = 1;
         let quest_name = "Test Quest".to_string();
         let quest_description = "Test Description".to_string();
-        let quest_reward = Reward { experience: Some(JobExperience { job: JobName::Woodcutter, amount: 100 }), items: None, gold: Some(100) };
+        let quest_reward = Reward { experience: Some(JobExperience { job: WOODCUTTER, amount: 100 }), items: None, gold: Some(100) };
         let quest_goal = Goal { objective: Objective::CollectGold(), required_amount: 100 };
 
         let quest_data = QuestData { id: quest_id, name: quest_name, description: quest_description, reward: quest_reward, goal: quest_goal };
         let quest = Quest::new(quest_id, false);
         let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
 
         assert_eq!(quest.check_completion(&quest_data, &player), false);
 
@@ -173,43 +177,48 @@ mod tests {
         let quest_id = 1;
         let quest_name = "Test Quest".to_string();
         let quest_description = "Test Description".to_string();
-        let quest_reward = Reward { experience: Some(JobExperience { job: JobName::Woodcutter, amount: 100 }), items: None, gold: Some(100) };
-        let quest_goal = Goal { objective: Objective::ReachJobLevel(JobName::Woodcutter), required_amount: 2 };
+        let quest_reward = Reward { experience: Some(JobExperience { job: WOODCUTTER, amount: 100 }), items: None, gold: Some(100) };
+        let quest_goal = Goal { objective: Objective::ReachJobLevel(WOODCUTTER), required_amount: 2 };
 
         let quest_data = QuestData { id: quest_id, name: quest_name, description: quest_description, reward: quest_reward, goal: quest_goal };
         let quest = Quest::new(quest_id, false);
         let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
 
-        let initial_player_job = player.get_job(JobName::Woodcutter).unwrap();
-        let needed_xp = initial_player_job.get_xp_needed_for_next_level();
+        let initial_player_job = player.get_job(WOODCUTTER).unwrap();
+        let needed_xp = initial_player_job.get_xp_needed_for_next_level().unwrap();
 
         assert_eq!(&initial_player_job.level, &1);
         assert_eq!(quest.check_completion(&quest_data, &player), false);
 
-        player.add_experience(JobName::Woodcutter, needed_xp);
+        player.add_experience(WOODCUTTER, needed_xp);
 
-        let updated_player_job = player.get_job(JobName::Woodcutter).unwrap();
+        let updated_player_job = player.get_job(WOODCUTTER).unwrap();
         assert_eq!(&updated_player_job.level, &2);
         assert_eq!(quest.check_completion(&quest_data, &player), true);        
     }
 
-    //TODO: Add real tests for level once we have some way to level up
     #[test]
     fn test_check_completion_works_for_level() {
         let quest_id = 1;
         let quest_name = "Test Quest".to_string();
         let quest_description = "Test Description".to_string();
-        let quest_reward = Reward { experience: Some(JobExperience { job: JobName::Woodcutter, amount: 100 }), items: None, gold: Some(100) };
+        let quest_reward = Reward { experience: Some(JobExperience { job: WOODCUTTER, amount: 100 }), items: None, gold: Some(100) };
         let quest_goal = Goal { objective: Objective::ReachLevel(), required_amount: 2 };
 
         let quest_data = QuestData { id: quest_id, name: quest_name, description: quest_description, reward: quest_reward, goal: quest_goal };
         let quest = Quest::new(quest_id, false);
         let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
 
         assert_eq!(quest.check_completion(&quest_data, &player), false);
 
-        player.level = 2;
+        // the player levels up with the experience of every job
+        player.add_experience(WOODCUTTER, 110);
+        assert!(!quest.check_completion(&quest_data, &player));
+        player.add_experience(MINER, 110);
 
+        assert_eq!(player.level, 2);
         assert_eq!(quest.check_completion(&quest_data, &player), true);
     }
 }
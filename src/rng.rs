@@ -0,0 +1,68 @@
+// A small seeded random number generator (SplitMix64). Its state is saved with the player, so
+// the same save always rolls the same loot.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Rng {
+    pub state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number from 0 to bound - 1, bound must not be 0
+    pub fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(bound)) >> 64) as u64
+    }
+
+    // A number from min to max, both included
+    pub fn range(&mut self, min: u128, max: u128) -> u128 {
+        if max <= min {
+            return min;
+        }
+        let span = max - min + 1;
+        match u64::try_from(span) {
+            Ok(span) => min + u128::from(self.below(span)),
+            Err(_) => min + u128::from(self.next_u64()) % span,
+        }
+    }
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_reproducible() {
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+        let rolls: Vec<u64> = (0..10).map(|_| first.next_u64()).collect();
+
+        assert_eq!(rolls, (0..10).map(|_| second.next_u64()).collect::<Vec<u64>>());
+        assert_ne!(Rng::new(43).next_u64(), rolls[0]);
+    }
+
+    #[test]
+    fn test_rng_range() {
+        let mut rng = Rng::new(7);
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            let value = rng.range(2, 4);
+            assert!((2..=4).contains(&value));
+            seen[(value - 2) as usize] = true;
+        }
+
+        assert_eq!(seen, [true; 3]);
+        assert_eq!(rng.range(5, 5), 5);
+    }
+}
@@ -1,56 +1,466 @@
 //save the game state to a file
 
+use crate::constants::PLAYER_DEFAULT_SEED;
 use crate::game_state::GameState;
+use crate::ledger::SessionLedger;
 use crate::player::Player;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use serde_json::Value;
+use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::BufReader;
 use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
 use crate::quest::Quest;
+use crate::utils::current_timestamp;
 
-pub fn save(game_state: &GameState, player: &Player, quests: &Vec<Quest>, save_name: &str) {
-    let file = File::create(save_name).unwrap();
-    let mut writer = BufWriter::new(file);
+#[derive(Debug)]
+pub enum SaveError {
+    Missing,
+    Corrupt(String),
+    UnsupportedVersion(u32),
+    InvalidSlot(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "The save file does not exist"),
+            Self::Corrupt(e) => write!(f, "The save file is corrupt: {}", e),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "The save file was written by a newer version of the game (save version {}, supported up to {})",
+                version, SAVE_VERSION
+            ),
+            Self::InvalidSlot(e) => write!(f, "Invalid save slot: {}", e),
+            Self::Io(e) => write!(f, "The save file could not be accessed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
 
+// A save that could not be loaded, where it was copied to and the backup the game was
+// restored from, if any
+pub struct LoadFailure {
+    pub error: SaveError,
+    pub backup_name: Option<String>,
+    pub restored_from: Option<String>,
+}
+
+impl LoadFailure {
+    // Overwriting is only safe if the unreadable save was copied, or if there was none
+    pub fn is_safe_to_overwrite(&self) -> bool {
+        self.backup_name.is_some() || matches!(self.error, SaveError::Missing)
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::Missing,
+            _ => Self::Io(e),
+        }
+    }
+}
+
+// Bump this and add a migration to MIGRATIONS whenever the saved structs change
+pub const SAVE_VERSION: u32 = 10;
+
+// MIGRATIONS[n] upgrades a save document from version n to version n + 1
+type Migration = fn(&mut Value) -> Result<(), String>;
+const MIGRATIONS: [Migration; SAVE_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    migrate_v8_to_v9,
+    migrate_v9_to_v10,
+];
+
+// The save is written to a temporary file which then replaces the previous save, so a crash
+// while saving never leaves a truncated file. The previous saves are kept as save_name.1
+// (newest) to save_name.<backup_count> (oldest).
+pub fn save(
+    game_state: &GameState,
+    player: &Player,
+    quests: &Vec<Quest>,
+    ledger: &SessionLedger,
+    save_name: &str,
+    backup_count: usize,
+) -> Result<(), SaveError> {
     //save the game state, player to the file
     let save = Save {
+        version: SAVE_VERSION,
         game_state: game_state.clone(),
         player: player.clone(),
         quests: quests.clone(),
-        timestamp: std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs(),
+        ledger: ledger.clone(),
+        timestamp: current_timestamp(),
     };
 
-    serde_json::to_writer_pretty(&mut writer, &save).unwrap();
+    let temp_name = format!("{}.tmp", save_name);
+    let file = File::create(&temp_name)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &save).map_err(|e| SaveError::Io(e.into()))?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    drop(writer);
+
+    rotate_backups(save_name, backup_count)?;
+    std::fs::rename(&temp_name, save_name)?;
+    Ok(())
+}
+
+pub fn backup_name(save_name: &str, index: usize) -> String {
+    format!("{}.{}", save_name, index)
+}
+
+// Shifts save_name.1 to save_name.2 and so on, dropping the oldest, then copies the
+// current save to save_name.1
+fn rotate_backups(save_name: &str, backup_count: usize) -> Result<(), SaveError> {
+    if backup_count == 0 || !Path::new(save_name).exists() {
+        return Ok(());
+    }
+    for index in (1..backup_count).rev() {
+        let from = backup_name(save_name, index);
+        if Path::new(&from).exists() {
+            std::fs::rename(&from, backup_name(save_name, index + 1))?;
+        }
+    }
+    std::fs::copy(save_name, backup_name(save_name, 1))?;
+    Ok(())
+}
+
+// The newest backup that loads successfully, with its file name
+pub fn load_newest_backup(save_name: &str, backup_count: usize) -> Option<(String, Save)> {
+    (1..=backup_count).find_map(|index| {
+        let name = backup_name(save_name, index);
+        match load(&name) {
+            Ok(save) => Some((name, save)),
+            Err(SaveError::Missing) => None,
+            Err(e) => {
+                println!("Skipping backup {}: {}", name, e);
+                None
+            }
+        }
+    })
+}
+
+pub fn load(save_name: &str) -> Result<Save, SaveError> {
+    let file = File::open(save_name)?;
+    let mut reader = BufReader::new(file);
+    let document: Value = serde_json::from_reader(&mut reader).map_err(|e| {
+        if e.is_io() {
+            SaveError::Io(e.into())
+        } else {
+            SaveError::Corrupt(e.to_string())
+        }
+    })?;
+    parse(document)
+}
+
+// Upgrades a save document of any known version to the current one and deserializes it
+pub fn parse(mut document: Value) -> Result<Save, SaveError> {
+    let version = save_version(&document).map_err(SaveError::Corrupt)?;
+    if version > SAVE_VERSION {
+        return Err(SaveError::UnsupportedVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut document).map_err(SaveError::Corrupt)?;
+    }
+    document["version"] = Value::from(SAVE_VERSION);
+
+    serde_json::from_value(document).map_err(|e| SaveError::Corrupt(e.to_string()))
+}
+
+// Copies a save that could not be loaded next to it so it is not lost when the game saves again
+pub fn backup_unreadable(save_name: &str) -> Result<String, SaveError> {
+    let timestamp = current_timestamp();
+    let backup_name = format!("{}.unreadable-{}", save_name, timestamp);
+    std::fs::copy(save_name, &backup_name)?;
+    Ok(backup_name)
+}
+
+// Saves written before versioning was introduced have no version field
+fn save_version(document: &Value) -> Result<u32, String> {
+    match document.get("version") {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("invalid save version: {}", version)),
+    }
+}
+
+fn player_mut(document: &mut Value) -> Result<&mut serde_json::Map<String, Value>, String> {
+    document
+        .get_mut("player")
+        .and_then(Value::as_object_mut)
+        .ok_or_else(|| "save has no player".to_string())
+}
+
+// v1: activities are identified by their id in the activity database instead of the
+// ActivityName enum, and the player can have a crafting in progress
+fn migrate_v0_to_v1(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    if let Some(activity) = player.get_mut("current_activity").and_then(Value::as_object_mut) {
+        let id = match activity.get("name").and_then(Value::as_str) {
+            Some("Woodcutting") => 1,
+            Some("Mining") => 2,
+            Some("Farming") => 3,
+            name => return Err(format!("unknown activity: {:?}", name)),
+        };
+        activity.insert("id".to_string(), Value::from(id));
+    }
+    player.entry("current_crafting").or_insert(Value::Null);
+    Ok(())
 }
 
-pub fn load(save_name: &str) -> Option<(GameState, Player, Vec<Quest>, u64)> {
-    match File::open(save_name) {
-        Ok(file) => {
-            let mut reader = BufReader::new(file);
-            let save: Save = match serde_json::from_reader(&mut reader) {
-                Ok(save) => save,
-                Err(_) => return None,
-            };
-            Some((save.game_state, save.player, save.quests, save.timestamp))
+// v2: activity and crafting durations and timers are whole milliseconds instead of f32 seconds
+fn migrate_v1_to_v2(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    for key in ["current_activity", "current_crafting"] {
+        if let Some(progress) = player.get_mut(key).and_then(Value::as_object_mut) {
+            for (seconds_key, milliseconds_key) in [("duration", "duration_ms"), ("timer", "timer_ms")] {
+                let seconds = progress
+                    .remove(seconds_key)
+                    .and_then(|seconds| seconds.as_f64())
+                    .ok_or_else(|| format!("{} has no {}", key, seconds_key))?;
+                let milliseconds = (seconds * 1000.0).round().max(0.0) as u64;
+                progress.insert(milliseconds_key.to_string(), Value::from(milliseconds));
+            }
+        }
+    }
+    Ok(())
+}
+
+// v3: the save keeps a ledger of play sessions. Older saves start it from their timestamp,
+// which is the only time they recorded.
+fn migrate_v2_to_v3(document: &mut Value) -> Result<(), String> {
+    let timestamp = document
+        .get("timestamp")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "save has no timestamp".to_string())?;
+    document["ledger"] = serde_json::json!({
+        "last_seen": timestamp,
+        "latest_seen": timestamp,
+        "play_time_secs": 0,
+        "session_count": 0,
+        "sessions": [],
+        "anomalies": [],
+    });
+    Ok(())
+}
+
+// The ids the JobName variants were given in the job database
+fn job_id(name: &Value) -> Result<u64, String> {
+    match name.as_str() {
+        Some("Woodcutter") => Ok(1),
+        Some("Miner") => Ok(2),
+        Some("Farmer") => Ok(3),
+        _ => Err(format!("unknown job: {}", name)),
+    }
+}
+
+// v4: jobs are defined in the job database and identified by their id instead of the JobName
+// enum. The jobs get their icon back from the database when the game starts.
+fn migrate_v3_to_v4(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    if let Some(jobs) = player.get_mut("jobs").and_then(Value::as_array_mut) {
+        for job in jobs.iter_mut().filter_map(Value::as_object_mut) {
+            let id = job_id(job.get("name").unwrap_or(&Value::Null))?;
+            job.insert("id".to_string(), Value::from(id));
+            job.entry("icon").or_insert(Value::from(""));
+        }
+    }
+    if let Some(experience) = player
+        .get_mut("current_activity")
+        .and_then(|activity| activity.get_mut("experience"))
+        .and_then(Value::as_array_mut)
+    {
+        for pair in experience.iter_mut().filter_map(Value::as_array_mut) {
+            if let Some(name) = pair.first_mut() {
+                *name = Value::from(job_id(name)?);
+            }
+        }
+    }
+    if let Some(job) = player.get_mut("current_crafting").and_then(|crafting| crafting.get_mut("job")) {
+        *job = Value::from(job_id(job)?);
+    }
+    Ok(())
+}
+
+// v5: jobs refer to a curve in the XP curve database instead of saving their own copy of
+// the level up table. Every job used the same table, which is now curve 1.
+fn migrate_v4_to_v5(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    if let Some(jobs) = player.get_mut("jobs").and_then(Value::as_array_mut) {
+        for job in jobs.iter_mut().filter_map(Value::as_object_mut) {
+            job.remove("level_up_experience");
+            job.insert("xp_curve".to_string(), Value::from(1));
+        }
+    }
+    Ok(())
+}
+
+// v6: the activity keeps the progress towards its next bonus items from job perks
+fn migrate_v5_to_v6(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    if let Some(activity) = player.get_mut("current_activity").and_then(Value::as_object_mut) {
+        activity.insert("bonus_progress".to_string(), Value::from(0));
+    }
+    Ok(())
+}
+
+// v7: jobs can be prestiged
+fn migrate_v6_to_v7(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    if let Some(jobs) = player.get_mut("jobs").and_then(Value::as_array_mut) {
+        for job in jobs.iter_mut().filter_map(Value::as_object_mut) {
+            job.insert("prestige".to_string(), Value::from(0));
+            job.insert("prestige_experience".to_string(), Value::from(0));
+        }
+    }
+    Ok(())
+}
+
+// v8: activities roll loot tables with a random number generator saved with the player. The
+// generator is seeded from the save timestamp, the current activity gets its loot table the
+// next time it is started.
+fn migrate_v7_to_v8(document: &mut Value) -> Result<(), String> {
+    let seed = document.get("timestamp").and_then(Value::as_u64).unwrap_or(PLAYER_DEFAULT_SEED);
+    let player = player_mut(document)?;
+
+    player.insert("rng".to_string(), serde_json::json!({ "state": seed }));
+    if let Some(activity) = player.get_mut("current_activity").and_then(Value::as_object_mut) {
+        activity.insert("loot".to_string(), Value::Null);
+    }
+    Ok(())
+}
+
+// v9: the player can equip items
+fn migrate_v8_to_v9(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    player.insert("equipment".to_string(), Value::Array(vec![]));
+    Ok(())
+}
+
+// v10: the player keeps track of what was bought from the vendors
+fn migrate_v9_to_v10(document: &mut Value) -> Result<(), String> {
+    let player = player_mut(document)?;
+
+    player.insert("shop".to_string(), serde_json::json!({ "sold": [] }));
+    Ok(())
+}
+
+// Saves periodically, or when asked to, without blocking the caller: the state is cloned
+// on the calling thread and written to disk on a background thread
+pub struct Autosaver {
+    interval: Duration,
+    last_save: Instant,
+    requested: bool,
+    in_progress: Option<JoinHandle<Result<(), SaveError>>>,
+}
+
+impl Autosaver {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_save: Instant::now(),
+            requested: false,
+            in_progress: None,
+        }
+    }
+
+    // Save as soon as possible instead of waiting for the interval, e.g. after a level up
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.requested || self.last_save.elapsed() >= self.interval
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.in_progress.as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    // Starts a background save if one is due and the previous one has finished
+    pub fn update(
+        &mut self,
+        game_state: &GameState,
+        player: &Player,
+        quests: &[Quest],
+        ledger: &mut SessionLedger,
+        save_name: &str,
+        backup_count: usize,
+    ) {
+        if !self.is_due() || self.is_saving() {
+            return;
+        }
+        self.wait();
+
+        ledger.checkpoint(current_timestamp());
+        let ledger = ledger.clone();
+        let game_state = game_state.clone();
+        let player = player.clone();
+        let quests = quests.to_vec();
+        let save_name = save_name.to_string();
+        self.in_progress = Some(std::thread::spawn(move || {
+            save(&game_state, &player, &quests, &ledger, &save_name, backup_count)
+        }));
+        self.last_save = Instant::now();
+        self.requested = false;
+    }
+
+    // Blocks until the background save in progress, if any, is written
+    pub fn wait(&mut self) {
+        if let Some(handle) = self.in_progress.take() {
+            match handle.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => println!("Error autosaving: {}", e),
+                Err(_) => println!("Error autosaving: the save thread panicked"),
+            }
         }
-        Err(_) => None,
     }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Save {
+    pub version: u32,
     pub game_state: GameState,
     pub player: Player,
     pub quests: Vec<Quest>,
+    pub ledger: SessionLedger,
     pub timestamp: u64,
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::job::mock::*;
+    use crate::rng::Rng;
+    use crate::xp_curve::mock::*;
 
     #[test]
     fn test_save() {
@@ -63,7 +473,7 @@ mod tests {
             .as_secs();
         let save_name = "test_save.json";
 
-        save(&game_state, &player, &quests, save_name);
+        save(&game_state, &player, &quests, &SessionLedger::new(), save_name, 0).unwrap();
 
         let file = File::open(save_name).unwrap();
         let mut reader = BufReader::new(file);
@@ -85,15 +495,274 @@ mod tests {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs();
-        let save_name = "test_save.json";
+        let save_name = std::env::temp_dir().join("idle_rust_test_save_and_load.json");
+        let save_name = save_name.to_str().unwrap();
+
+        save(&game_state, &player, &quests, &SessionLedger::new(), save_name, 0).unwrap();
+
+        let loaded = load(save_name).unwrap();
+
+        assert_eq!(game_state, loaded.game_state);
+        assert_eq!(player, loaded.player);
+        assert_eq!(quests, loaded.quests);
+        assert_eq!(timestamp, loaded.timestamp);
+    }
+
+    #[test]
+    fn test_load_v0_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v0.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.game_state, GameState::Activity);
+        assert_eq!(save.timestamp, 1745000000);
+        assert_eq!(save.quests.len(), 3);
+        assert_eq!(save.player.inventory.get_item_quantity(1), 12);
+        assert_eq!(save.player.jobs[1].level, 3);
+        let activity = save.player.current_activity.unwrap();
+        assert_eq!(activity.id, 2);
+        assert_eq!(activity.name, "Mining");
+        assert_eq!(activity.timer_ms, 3500);
+        assert_eq!(activity.duration_ms, 10000);
+        assert_eq!(save.player.current_crafting, None);
+    }
+
+    #[test]
+    fn test_load_v1_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v1.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.game_state, GameState::Crafting);
+        let activity = save.player.current_activity.unwrap();
+        assert_eq!(activity.id, 1);
+        assert_eq!(activity.duration_ms, 10000);
+        assert_eq!(activity.timer_ms, 7250);
+        let crafting = save.player.current_crafting.unwrap();
+        assert_eq!(crafting.recipe_id, 1);
+        assert_eq!(crafting.duration_ms, 5000);
+        assert_eq!(crafting.timer_ms, 1500);
+        assert_eq!(crafting.remaining, 4);
+    }
+
+    #[test]
+    fn test_load_v2_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v2.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.ledger.last_seen, save.timestamp);
+        assert_eq!(save.ledger.latest_seen, save.timestamp);
+        assert_eq!(save.ledger.session_count, 0);
+        assert_eq!(save.ledger.play_time_secs, 0);
+    }
+
+    #[test]
+    fn test_load_v3_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v3.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        let ids: Vec<u128> = save.player.jobs.iter().map(|job| job.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(save.player.get_job(2).unwrap().level, 4);
+        assert_eq!(save.player.current_activity.unwrap().experience, vec![(1, 100)]);
+        assert_eq!(save.player.current_crafting.unwrap().job, 1);
+        assert_eq!(save.ledger.session_count, 2);
+    }
+
+    #[test]
+    fn test_load_v4_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v4.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert!(save.player.jobs.iter().all(|job| job.xp_curve == 1));
+        let farmer = save.player.get_job(3).unwrap();
+        assert_eq!(farmer.level, 1);
+        assert_eq!(farmer.experience, 250);
+        // the curve is looked up from the database once the game starts
+        let mut player = save.player.clone();
+        player.update_data(&job_database(), &xp_curve_database());
+        assert_eq!(player.get_job(3).unwrap().level, 2);
+        assert_eq!(player.get_job(3).unwrap().experience, 140);
+    }
+
+    #[test]
+    fn test_load_v5_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v5.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        let activity = save.player.current_activity.unwrap();
+        assert_eq!(activity.bonus_progress, 0);
+        assert_eq!(activity.timer_ms, 7250);
+    }
+
+    #[test]
+    fn test_load_v6_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v6.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert!(save.player.jobs.iter().all(|job| job.prestige == 0 && job.prestige_experience == 0));
+        assert_eq!(save.player.get_job(2).unwrap().level, 4);
+    }
+
+    #[test]
+    fn test_load_v7_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v7.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.player.rng, Rng::new(1746000000));
+        let mut player = save.player;
+        let activity = player.current_activity.as_ref().unwrap();
+        assert_eq!(activity.loot, None);
+        assert_eq!(activity.timer_ms, 7250);
+
+        // the loot table comes back from the activity database on load
+        let activity_database = crate::utils::load_activity_database().unwrap();
+        player.update_activity_data(&activity_database);
+        let activity = player.current_activity.unwrap();
+        assert_eq!(activity.loot, activity_database[&1].loot);
+        assert!(activity.loot.is_some());
+        assert_eq!(activity.timer_ms, 7250);
+    }
+
+    #[test]
+    fn test_load_v8_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v8.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert!(save.player.equipment.is_empty());
+        assert_eq!(save.player.rng, Rng::new(1746000000));
+        assert_eq!(save.player.inventory.get_item_quantity(7), 3);
+    }
+
+    #[test]
+    fn test_load_v9_fixture() {
+        let document: Value = serde_json::from_str(include_str!("../tests/fixtures/save_v9.json")).unwrap();
+
+        let save = parse(document).unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert!(save.player.shop.sold.is_empty());
+        assert_eq!(save.player.equipment[0].item_id, 11);
+        assert_eq!(save.player.inventory.gold, 250);
+    }
+
+    #[test]
+    fn test_load_rejects_newer_version() {
+        let mut document = serde_json::to_value(Save {
+            version: SAVE_VERSION,
+            game_state: GameState::new(),
+            player: Player::new(),
+            quests: vec![],
+            ledger: SessionLedger::new(),
+            timestamp: 0,
+        })
+        .unwrap();
+        document["version"] = Value::from(SAVE_VERSION + 1);
+
+        assert!(matches!(parse(document), Err(SaveError::UnsupportedVersion(version)) if version == SAVE_VERSION + 1));
+    }
+
+    #[test]
+    fn test_load_missing_save() {
+        let save_name = std::env::temp_dir().join("idle_rust_missing_save.json");
+        let _ = std::fs::remove_file(&save_name);
+
+        assert!(matches!(load(save_name.to_str().unwrap()), Err(SaveError::Missing)));
+    }
+
+    #[test]
+    fn test_load_corrupt_save_and_back_it_up() {
+        let save_name = std::env::temp_dir().join("idle_rust_corrupt_save.json");
+        let save_name = save_name.to_str().unwrap();
+        std::fs::write(save_name, "{ \"player\": ").unwrap();
+
+        assert!(matches!(load(save_name), Err(SaveError::Corrupt(_))));
+
+        let backup_name = backup_unreadable(save_name).unwrap();
+        assert_eq!(std::fs::read_to_string(&backup_name).unwrap(), "{ \"player\": ");
+
+        std::fs::remove_file(save_name).unwrap();
+        std::fs::remove_file(backup_name).unwrap();
+    }
+
+    #[test]
+    fn test_save_rotates_backups() {
+        let save_name = std::env::temp_dir().join("idle_rust_rotating_save.json");
+        let save_name = save_name.to_str().unwrap();
+        let mut player = Player::new();
+
+        for gold in 1..=4 {
+            player.inventory.gold = gold;
+            save(&GameState::new(), &player, &vec![], &SessionLedger::new(), save_name, 2).unwrap();
+        }
+
+        assert_eq!(load(save_name).unwrap().player.inventory.gold, 4);
+        assert_eq!(load(&backup_name(save_name, 1)).unwrap().player.inventory.gold, 3);
+        assert_eq!(load(&backup_name(save_name, 2)).unwrap().player.inventory.gold, 2);
+        assert!(!Path::new(&backup_name(save_name, 3)).exists());
+        assert!(!Path::new(&format!("{}.tmp", save_name)).exists());
+
+        for name in [save_name.to_string(), backup_name(save_name, 1), backup_name(save_name, 2)] {
+            std::fs::remove_file(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_load_newest_backup_skips_unreadable_backups() {
+        let save_name = std::env::temp_dir().join("idle_rust_fallback_save.json");
+        let save_name = save_name.to_str().unwrap();
+        let mut player = Player::new();
+        player.inventory.gold = 42;
+        save(&GameState::new(), &player, &vec![], &SessionLedger::new(), &backup_name(save_name, 2), 0).unwrap();
+        std::fs::write(backup_name(save_name, 1), "").unwrap();
+        std::fs::write(save_name, "not json").unwrap();
+
+        assert!(matches!(load(save_name), Err(SaveError::Corrupt(_))));
+        let (name, save) = load_newest_backup(save_name, 3).unwrap();
+        assert_eq!(name, backup_name(save_name, 2));
+        assert_eq!(save.player.inventory.gold, 42);
+
+        for name in [save_name.to_string(), backup_name(save_name, 1), backup_name(save_name, 2)] {
+            std::fs::remove_file(name).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_autosaver_saves_when_requested() {
+        let save_name = std::env::temp_dir().join("idle_rust_autosave.json");
+        let save_name = save_name.to_str().unwrap();
+        let _ = std::fs::remove_file(save_name);
+        let mut player = Player::new();
+        player.inventory.gold = 7;
+        let mut autosaver = Autosaver::new(Duration::from_secs(3600));
 
-        save(&game_state, &player, &quests, save_name);
+        autosaver.update(&GameState::new(), &player, &[], &mut SessionLedger::new(), save_name, 0);
+        autosaver.wait();
+        assert!(!Path::new(save_name).exists());
 
-        let (loaded_game_state, loaded_player, loaded_quests, loaded_timestamp) = load(save_name).unwrap();
+        autosaver.request();
+        assert!(autosaver.is_due());
+        autosaver.update(&GameState::new(), &player, &[], &mut SessionLedger::new(), save_name, 0);
+        assert!(!autosaver.is_due());
+        autosaver.wait();
+        assert_eq!(load(save_name).unwrap().player.inventory.gold, 7);
 
-        assert_eq!(game_state, loaded_game_state);
-        assert_eq!(player, loaded_player);
-        assert_eq!(quests, loaded_quests);
-        assert_eq!(timestamp, loaded_timestamp);
+        std::fs::remove_file(save_name).unwrap();
     }
 }
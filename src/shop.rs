@@ -0,0 +1,233 @@
+// Vendors are defined in vendors.json. They sell a limited stock of items that refills some
+// time after it was first bought from, and buy the items of some categories for their sell
+// value. The stock sold so far is saved with the player.
+
+use crate::inventory::Inventory;
+use crate::item::{Item, ItemCategory, ItemData};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct StockData {
+    pub item_id: u128,
+    // gold for one
+    pub price: u128,
+    // how many can be bought before the stock runs out
+    pub stock: u128,
+    // seconds after the first purchase until the stock is full again
+    pub restock_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct VendorData {
+    pub id: u128,
+    pub name: String,
+    pub description: String,
+    pub stock: Vec<StockData>,
+    // the vendor buys the items of these categories for their sell value
+    pub buys: Vec<ItemCategory>,
+}
+
+// What was bought from a stock since it was last full
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SoldStock {
+    pub vendor_id: u128,
+    pub item_id: u128,
+    pub sold: u128,
+    // timestamp of the first purchase, in seconds
+    pub since: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Shop {
+    pub sold: Vec<SoldStock>,
+}
+
+impl VendorData {
+    pub fn get_stock(&self, item_id: u128) -> Option<&StockData> {
+        self.stock.iter().find(|stock| stock.item_id == item_id)
+    }
+
+    pub fn buys_item(&self, item_data: &ItemData) -> bool {
+        self.buys.contains(&item_data.category)
+    }
+}
+
+impl Shop {
+    pub fn new() -> Self {
+        Self { sold: vec![] }
+    }
+
+    // How many are left to buy at the time now
+    pub fn remaining(&self, vendor_id: u128, stock: &StockData, now: u64) -> u128 {
+        match self.find(vendor_id, stock.item_id) {
+            Some(sold) if now < sold.since.saturating_add(stock.restock_secs) => stock.stock.saturating_sub(sold.sold),
+            _ => stock.stock,
+        }
+    }
+
+    // When the stock will be full again, None if it is full
+    pub fn restocks_at(&self, vendor_id: u128, stock: &StockData, now: u64) -> Option<u64> {
+        match self.find(vendor_id, stock.item_id) {
+            Some(sold) if now < sold.since.saturating_add(stock.restock_secs) => {
+                Some(sold.since.saturating_add(stock.restock_secs))
+            }
+            _ => None,
+        }
+    }
+
+    // Takes the gold and gives the items, or does nothing if the stock, the gold or the stack
+    // limit of the item is not enough
+    pub fn buy(
+        &mut self,
+        vendor: &VendorData,
+        item_data: &ItemData,
+        quantity: u128,
+        inventory: &mut Inventory,
+        now: u64,
+    ) -> Result<(), String> {
+        let stock = match vendor.get_stock(item_data.id) {
+            Some(stock) => stock,
+            None => return Err(format!("{} does not sell {}", vendor.name, item_data.name)),
+        };
+        let remaining = self.remaining(vendor.id, stock, now);
+        if quantity > remaining {
+            return Err(format!("{} has only {} {} left", vendor.name, remaining, item_data.name));
+        }
+        if let Some(stack_limit) = item_data.stack_limit {
+            if inventory.get_item_quantity(item_data.id) + quantity > stack_limit {
+                return Err(format!("You can not hold more than {} {}", stack_limit, item_data.name));
+            }
+        }
+        inventory.remove_gold(stock.price.saturating_mul(quantity))?;
+        let overflow = inventory.add_item(&Item::new(item_data.id, quantity));
+        debug_assert_eq!(overflow, 0);
+
+        let full = self.remaining(vendor.id, stock, now) == stock.stock;
+        match self.sold.iter_mut().find(|sold| sold.vendor_id == vendor.id && sold.item_id == item_data.id) {
+            Some(sold) if full => *sold = SoldStock { vendor_id: vendor.id, item_id: item_data.id, sold: quantity, since: now },
+            Some(sold) => sold.sold += quantity,
+            None => self.sold.push(SoldStock { vendor_id: vendor.id, item_id: item_data.id, sold: quantity, since: now }),
+        }
+        Ok(())
+    }
+
+    // Takes the items and gives their sell value
+    pub fn sell(vendor: &VendorData, item_data: &ItemData, quantity: u128, inventory: &mut Inventory) -> Result<(), String> {
+        if !vendor.buys_item(item_data) {
+            return Err(format!("{} does not buy {}", vendor.name, item_data.name));
+        }
+        inventory.remove_item(Item::new(item_data.id, quantity))?;
+        inventory.add_gold(item_data.sell_value.saturating_mul(quantity));
+        Ok(())
+    }
+
+    fn find(&self, vendor_id: u128, item_id: u128) -> Option<&SoldStock> {
+        self.sold.iter().find(|sold| sold.vendor_id == vendor_id && sold.item_id == item_id)
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::ledger::SessionLedger;
+
+    fn general_store() -> VendorData {
+        VendorData {
+            id: 1,
+            name: "General Store".to_string(),
+            description: String::new(),
+            stock: vec![StockData { item_id: 3, price: 5, stock: 10, restock_secs: 3600 }],
+            buys: vec![ItemCategory::Resource],
+        }
+    }
+
+    fn potato() -> ItemData {
+        ItemData { stack_limit: Some(15), ..ItemData::new(3, "Potato", "", ItemCategory::Food) }
+    }
+
+    fn log() -> ItemData {
+        ItemData { sell_value: 2, ..ItemData::new(1, "Log", "", ItemCategory::Resource) }
+    }
+
+    #[test]
+    fn test_buy_takes_gold_and_stock() {
+        let vendor = general_store();
+        let mut shop = Shop::new();
+        let mut inventory = Inventory::new();
+        inventory.add_gold(100);
+
+        shop.buy(&vendor, &potato(), 4, &mut inventory, 1000).unwrap();
+
+        assert_eq!(inventory.gold, 80);
+        assert_eq!(inventory.get_item_quantity(3), 4);
+        assert_eq!(shop.remaining(1, &vendor.stock[0], 1000), 6);
+        assert_eq!(shop.restocks_at(1, &vendor.stock[0], 1000), Some(4600));
+        assert!(shop.buy(&vendor, &potato(), 7, &mut inventory, 2000).is_err());
+        assert!(shop.buy(&vendor, &log(), 1, &mut inventory, 2000).is_err());
+    }
+
+    #[test]
+    fn test_buy_fails_without_enough_gold_or_room() {
+        let vendor = general_store();
+        let mut shop = Shop::new();
+        let mut inventory = Inventory::new();
+        inventory.add_gold(10);
+
+        assert!(shop.buy(&vendor, &potato(), 3, &mut inventory, 0).is_err());
+        assert_eq!(inventory.gold, 10);
+        assert_eq!(shop.remaining(1, &vendor.stock[0], 0), 10);
+
+        inventory.add_gold(100);
+        assert_eq!(inventory.add_item(&Item::new(3, 14)), 0);
+        assert!(shop.buy(&vendor, &potato(), 2, &mut inventory, 0).is_err());
+        assert_eq!(inventory.gold, 110);
+    }
+
+    #[test]
+    fn test_stock_restocks() {
+        let vendor = general_store();
+        let mut shop = Shop::new();
+        let mut inventory = Inventory::new();
+        inventory.add_gold(1000);
+
+        shop.buy(&vendor, &potato(), 10, &mut inventory, 0).unwrap();
+        assert_eq!(shop.remaining(1, &vendor.stock[0], 3599), 0);
+        assert_eq!(shop.remaining(1, &vendor.stock[0], 3600), 10);
+
+        // a full stock starts a new restock timer
+        shop.buy(&vendor, &potato(), 1, &mut inventory, 5000).unwrap();
+        assert_eq!(shop.remaining(1, &vendor.stock[0], 5000), 9);
+        assert_eq!(shop.restocks_at(1, &vendor.stock[0], 5000), Some(8600));
+    }
+
+    #[test]
+    fn test_moving_the_clock_does_not_restock() {
+        let vendor = general_store();
+        let mut shop = Shop::new();
+        let mut inventory = Inventory::new();
+        inventory.add_gold(1000);
+        let mut ledger = SessionLedger::new();
+        ledger.start_session(1_000_000);
+
+        shop.buy(&vendor, &potato(), 10, &mut inventory, ledger.now()).unwrap();
+        // the clock is moved a day forward while playing
+        ledger.checkpoint(1_000_000 + 86_400);
+
+        assert_eq!(shop.remaining(1, &vendor.stock[0], ledger.now()), 0);
+        assert!(shop.buy(&vendor, &potato(), 1, &mut inventory, ledger.now()).is_err());
+    }
+
+    #[test]
+    fn test_sell_gives_sell_value() {
+        let vendor = general_store();
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(&Item::new(1, 5)), 0);
+
+        Shop::sell(&vendor, &log(), 3, &mut inventory).unwrap();
+
+        assert_eq!(inventory.gold, 6);
+        assert_eq!(inventory.get_item_quantity(1), 2);
+        assert!(Shop::sell(&vendor, &log(), 3, &mut inventory).is_err());
+        assert!(Shop::sell(&vendor, &potato(), 1, &mut inventory).is_err());
+        assert_eq!(inventory.gold, 6);
+    }
+}
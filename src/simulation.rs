@@ -0,0 +1,278 @@
+// The game advances in fixed ticks of SIMULATION_TICK_MS, the same way whether it is running
+// or catching up on offline time, so the result only depends on how much time passed and not
+// on how it was split into frames. The one exception is loot: a step of more than
+// LOOT_SAMPLE_LIMIT rolls gives every entry its expected share instead of sampling the rolls,
+// so a long absence gets the average loot rather than what playing would have rolled.
+
+use crate::constants::SIMULATION_TICK_MS;
+use crate::player::Player;
+use std::ops::AddAssign;
+
+// What completed while the simulation advanced
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Progress {
+    pub cycles: u128,
+    pub crafts: u128,
+    // player levels gained
+    pub levels: u8,
+}
+
+impl AddAssign for Progress {
+    fn add_assign(&mut self, other: Self) {
+        self.cycles += other.cycles;
+        self.crafts += other.crafts;
+        self.levels = self.levels.saturating_add(other.levels);
+    }
+}
+
+pub struct Simulation {
+    // time that does not make up a whole tick yet
+    carry_ms: u64,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self { carry_ms: 0 }
+    }
+
+    // Runs every whole tick in the elapsed time, the rest carries over to the next call
+    pub fn advance(&mut self, player: &mut Player, elapsed_ms: u64) -> Result<Progress, String> {
+        let total_ms = u128::from(self.carry_ms) + u128::from(elapsed_ms);
+        let ticks = total_ms / u128::from(SIMULATION_TICK_MS);
+        self.carry_ms = (total_ms % u128::from(SIMULATION_TICK_MS)) as u64;
+        run_ticks(player, ticks as u64)
+    }
+}
+
+// The activity cycles between two events are rewarded in one step, so a month away costs about
+// as much as a few minutes. At the end of a tick the activity is rewarded before the crafting,
+// so the crafting can use what the activity just gathered.
+pub fn run_ticks(player: &mut Player, ticks: u64) -> Result<Progress, String> {
+    let (progress, _) = run_ticks_until(player, ticks, |_| false)?;
+    Ok(progress)
+}
+
+// Like run_ticks, but stops at the first tick after which is_done holds. Also returns the
+// number of ticks that were run.
+pub fn run_ticks_until(
+    player: &mut Player,
+    ticks: u64,
+    is_done: impl FnMut(&Player) -> bool,
+) -> Result<(Progress, u64), String> {
+    let (progress, ticks, _) = run_steps(player, ticks, is_done)?;
+    Ok((progress, ticks))
+}
+
+// Also returns the number of steps taken. When is_done starts to hold during a step, the
+// step is searched for the first tick it holds at.
+fn run_steps(
+    player: &mut Player,
+    ticks: u64,
+    mut is_done: impl FnMut(&Player) -> bool,
+) -> Result<(Progress, u64, u64), String> {
+    let mut progress = Progress::default();
+    let mut ticks_left = ticks;
+    let mut steps = 0;
+    while ticks_left > 0 && !is_done(player) {
+        let mut step = ticks_until_next_event(player).min(ticks_left);
+        let mut next = player.clone();
+        let mut step_progress = next.update(step.saturating_mul(SIMULATION_TICK_MS))?;
+        if step > 1 && is_done(&next) {
+            // is_done does not hold after 0 ticks and holds after step ticks
+            let mut not_done = 0;
+            while step - not_done > 1 {
+                let middle = not_done + (step - not_done) / 2;
+                let mut candidate = player.clone();
+                let candidate_progress = candidate.update(middle * SIMULATION_TICK_MS)?;
+                if is_done(&candidate) {
+                    (step, next, step_progress) = (middle, candidate, candidate_progress);
+                } else {
+                    not_done = middle;
+                }
+            }
+        }
+        *player = next;
+        progress += step_progress;
+        ticks_left -= step;
+        steps += 1;
+    }
+    Ok((progress, ticks - ticks_left, steps))
+}
+
+// The number of ticks until the next event that changes how the rest plays out, at least
+// one. Events are the crafting completing, as it needs what the activity gathered, and a job
+// the activity trains leveling up, as it can unlock perks and loot.
+fn ticks_until_next_event(player: &Player) -> u64 {
+    let mut remaining_ms = u128::MAX;
+    if let Some(activity) = &player.current_activity {
+        if let Some(cycles) = activity.cycles_until_level_up(&player.jobs) {
+            let cycle_ms = u128::from(activity.cycle_ms(&player.jobs, &player.equipment));
+            let current_ms = cycle_ms.saturating_sub(u128::from(activity.timer_ms));
+            remaining_ms = current_ms.saturating_add((cycles - 1).saturating_mul(cycle_ms));
+        }
+    }
+    if let Some(crafting) = player.current_crafting.as_ref().filter(|crafting| !crafting.is_finished()) {
+        remaining_ms = remaining_ms.min(u128::from(crafting.duration_ms.max(1).saturating_sub(crafting.timer_ms)));
+    }
+    let ticks = remaining_ms.div_ceil(u128::from(SIMULATION_TICK_MS)).max(1);
+    u64::try_from(ticks).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+    use crate::activity::Activity;
+    use crate::crafting::RecipeData;
+    use crate::item::Item;
+    use crate::loot::{LootEntry, LootTable};
+    use crate::offline::OfflineConfig;
+
+    // Cuts a log every 10s and turns every 2 logs into a plank every 15s, so whether the crafting
+    // runs out of logs depends on when the activity is rewarded
+    fn woodworking_player() -> Player {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        player.add_item(&Item::new(1, 2));
+        player.set_activity(Activity::new(
+            1,
+            "Woodcutting".to_string(),
+            "Cut down trees".to_string(),
+            10_000,
+            vec![(WOODCUTTER, 10)],
+            vec![Item::new(1, 1)],
+        ));
+        let recipe = RecipeData {
+            id: 1,
+            name: "Plank".to_string(),
+            description: "Saw a log into planks".to_string(),
+            inputs: vec![Item::new(1, 2)],
+            outputs: vec![Item::new(4, 1)],
+            job: WOODCUTTER,
+            required_level: 1,
+            duration_ms: 15_000,
+            experience: 5,
+        };
+        player.start_crafting(&recipe, 1000).unwrap();
+        player
+    }
+
+    #[test]
+    fn test_online_and_offline_progress_are_identical() {
+        let mut offline = woodworking_player();
+        offline.update_from_time_elapsed(3600, &OfflineConfig::unlimited()).unwrap();
+
+        // uneven frames, including a long stall
+        let mut online = woodworking_player();
+        let mut simulation = Simulation::new();
+        let mut elapsed_ms = 0;
+        for frame in 0.. {
+            let frame_ms = match frame % 4 {
+                0 => 16,
+                1 => 17,
+                2 => 33,
+                _ => 2_345,
+            };
+            let frame_ms = frame_ms.min(3_600_000 - elapsed_ms);
+            simulation.advance(&mut online, frame_ms).unwrap();
+            elapsed_ms += frame_ms;
+            if elapsed_ms == 3_600_000 {
+                break;
+            }
+        }
+
+        assert_eq!(online, offline);
+        assert!(online.inventory.get_item_quantity(4) > 0);
+    }
+
+    #[test]
+    fn test_simulation_carries_partial_ticks() {
+        let mut player = woodworking_player();
+        let mut simulation = Simulation::new();
+
+        for _ in 0..(10_000 / 40) - 1 {
+            assert_eq!(simulation.advance(&mut player, 40).unwrap(), Progress::default());
+        }
+        let progress = simulation.advance(&mut player, 40).unwrap();
+
+        assert_eq!(progress, Progress { cycles: 1, crafts: 0, levels: 0 });
+        assert_eq!(player.inventory.get_item_quantity(1), 1);
+    }
+
+    #[test]
+    fn test_run_ticks_until() {
+        let mut player = woodworking_player();
+
+        let (progress, ticks) =
+            run_ticks_until(&mut player, u64::MAX, |player| player.inventory.get_item_quantity(1) >= 3).unwrap();
+
+        // a log every 10s, while the plank crafted at 15s runs out of logs for the next one
+        assert_eq!(ticks * SIMULATION_TICK_MS, 30_000);
+        assert_eq!(progress, Progress { cycles: 3, crafts: 1, levels: 0 });
+        assert_eq!(player.current_crafting, None);
+    }
+
+    #[test]
+    fn test_a_month_away_takes_few_steps() {
+        let mut player = woodworking_player();
+        let mut per_tick = woodworking_player();
+
+        let ticks = 30 * 86_400_000 / SIMULATION_TICK_MS;
+        let (progress, _, steps) = run_steps(&mut player, ticks, |_| false).unwrap();
+
+        // one step per plank until the logs run out, then one per woodcutter level
+        assert_eq!(progress.cycles, 259_200);
+        assert!(steps < 100, "{} steps", steps);
+        assert_eq!(player.get_job(WOODCUTTER).unwrap().level, standard_curve().max_level());
+
+        // the same as stepping to every completion
+        for _ in 0..ticks / 100 {
+            run_steps(&mut per_tick, 100, |_| false).unwrap();
+        }
+        assert_eq!(per_tick, player);
+    }
+
+    #[test]
+    fn test_run_ticks_until_stops_inside_a_step() {
+        let mut player = woodworking_player();
+        player.cancel_crafting();
+
+        let (progress, ticks) =
+            run_ticks_until(&mut player, u64::MAX, |player| player.inventory.get_item_quantity(1) >= 1234).unwrap();
+
+        // the player starts with the two logs of the cancelled plank
+        assert_eq!(player.inventory.get_item_quantity(1), 1234);
+        assert_eq!(progress.cycles, 1232);
+        assert_eq!(ticks * SIMULATION_TICK_MS, 1232 * 10_000);
+    }
+
+    #[test]
+    fn test_long_absences_give_the_expected_loot() {
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+        let miner = player.jobs.iter_mut().find(|job| job.id == MINER).unwrap();
+        miner.level = miner.curve.max_level();
+        let mut activity =
+            Activity::new(2, "Mining".to_string(), "Mine rocks".to_string(), 1000, vec![(MINER, 10)], vec![]);
+        activity.loot = Some(LootTable {
+            rolls: 1,
+            empty_weight: 60,
+            entries: vec![LootEntry { item_id: 2, weight: 40, min_quantity: 1, max_quantity: 3, required_job: None }],
+        });
+        player.set_activity(activity);
+
+        let report = player.update_from_time_elapsed(100_000, &OfflineConfig::unlimited()).unwrap();
+
+        // the cycles are rolled at once, so 40% of them give 2 stones on average
+        assert_eq!(report.cycles, 100_000);
+        assert_eq!(player.inventory.get_item_quantity(2), 80_000);
+    }
+
+    #[test]
+    fn test_run_ticks_without_anything_to_do() {
+        let mut player = Player::new();
+
+        assert_eq!(run_ticks(&mut player, u64::MAX).unwrap(), Progress::default());
+    }
+}
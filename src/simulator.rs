@@ -0,0 +1,384 @@
+// Projects how long it takes to reach job levels and complete quests when following a plan of
+// activities, by running the simulation as fast as it can.
+//
+// idle_rust --simulate --plan <activity id>[:<duration>],... [--slot <name>] [--horizon <duration>]
+//           [--levels <level>,...] [--csv]
+//
+// Each step of the plan runs its activity for its duration, the last one until the horizon.
+// Quests are completed as soon as their goal is met, so their rewards count towards later
+// milestones. Without --slot the projection starts from a new player.
+
+use crate::activity::Activity;
+use crate::constants::SAVES_DIRECTORY;
+use crate::constants::SIMULATION_TICK_MS;
+use crate::constants::SIMULATOR_HORIZON_SECS;
+use crate::constants::SIMULATOR_LEVELS;
+use crate::player::Player;
+use crate::quest::Quest;
+use crate::save;
+use crate::simulation::run_ticks_until;
+use crate::slots;
+use crate::utils::format_duration;
+use crate::utils::load_activity_database;
+use crate::utils::load_item_database;
+use crate::utils::load_job_database;
+use crate::utils::load_xp_curve_database;
+use crate::utils::load_quest_database;
+use crate::utils::parse_duration;
+use crate::utils::ActivityDatabase;
+use crate::utils::QuestDatabase;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlanStep {
+    pub activity_id: u128,
+    pub duration: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Milestone {
+    // job id and level
+    JobLevel(u128, u8),
+    Quest(u128),
+}
+
+impl Milestone {
+    fn is_reached(&self, player: &Player, quest_database: &QuestDatabase) -> bool {
+        match self {
+            Self::JobLevel(id, level) => player.get_job(*id).is_some_and(|job| job.level >= *level),
+            Self::Quest(id) => quest_database
+                .get(id)
+                .is_some_and(|quest_data| Quest::new(*id, false).check_completion(quest_data, player)),
+        }
+    }
+}
+
+// When a milestone is reached, None if it is not reached within the horizon
+#[derive(Debug, PartialEq, Clone)]
+pub struct Projection {
+    pub milestone: Milestone,
+    // the job or quest name
+    pub name: String,
+    pub time_ms: Option<u64>,
+}
+
+impl Projection {
+    pub fn label(&self) -> String {
+        match &self.milestone {
+            Milestone::JobLevel(_, level) => format!("{} level {}", self.name, level),
+            Milestone::Quest(_) => format!("Quest: {}", self.name),
+        }
+    }
+}
+
+pub fn project(
+    player: &Player,
+    quests: &[Quest],
+    plan: &[PlanStep],
+    horizon: u64,
+    levels: &[u8],
+    activity_database: &ActivityDatabase,
+    quest_database: &QuestDatabase,
+) -> Result<Vec<Projection>, String> {
+    let mut player = player.clone();
+    let mut quests = quests.to_vec();
+    quests.sort_by_key(|quest| quest.id);
+
+    let mut projections = vec![];
+    for job in &player.jobs {
+        for level in levels.iter().filter(|level| **level > job.level && **level <= job.curve.max_level()) {
+            projections.push(Projection {
+                milestone: Milestone::JobLevel(job.id, *level),
+                name: job.name.clone(),
+                time_ms: None,
+            });
+        }
+    }
+    for quest in quests.iter().filter(|quest| !quest.completed) {
+        let name = quest_database.get(&quest.id).map_or("Unknown Quest", |quest_data| quest_data.name.as_str());
+        projections.push(Projection {
+            milestone: Milestone::Quest(quest.id),
+            name: name.to_string(),
+            time_ms: None,
+        });
+    }
+
+    let horizon_ticks = horizon.saturating_mul(1000) / SIMULATION_TICK_MS;
+    let mut elapsed_ticks = 0;
+    for (index, step) in plan.iter().enumerate() {
+        let activity_data = activity_database
+            .get(&step.activity_id)
+            .ok_or_else(|| format!("Activity not found: {}", step.activity_id))?;
+        player.set_activity(Activity::from_data(activity_data));
+
+        let step_ticks = match step.duration {
+            Some(duration) if index + 1 < plan.len() => duration.saturating_mul(1000) / SIMULATION_TICK_MS,
+            _ => u64::MAX,
+        };
+        let mut ticks_left = step_ticks.min(horizon_ticks - elapsed_ticks);
+        loop {
+            let elapsed_ms = elapsed_ticks * SIMULATION_TICK_MS;
+            for projection in projections.iter_mut().filter(|projection| projection.time_ms.is_none()) {
+                if projection.milestone.is_reached(&player, quest_database) {
+                    projection.time_ms = Some(elapsed_ms);
+                    if let Milestone::Quest(id) = projection.milestone {
+                        if let Some(quest) = quests.iter_mut().find(|quest| quest.id == id) {
+                            quest.complete(&mut player, quest_database);
+                        }
+                    }
+                }
+            }
+
+            let pending: Vec<&Milestone> = projections
+                .iter()
+                .filter(|projection| projection.time_ms.is_none())
+                .map(|projection| &projection.milestone)
+                .collect();
+            if ticks_left == 0 || pending.is_empty() {
+                break;
+            }
+            let (_, ticks) = run_ticks_until(&mut player, ticks_left, |player| {
+                pending.iter().any(|milestone| milestone.is_reached(player, quest_database))
+            })?;
+            elapsed_ticks += ticks;
+            ticks_left -= ticks;
+        }
+    }
+    Ok(projections)
+}
+
+// Reached milestones sorted by time, then the ones that were not reached
+pub fn format_table(projections: &[Projection]) -> String {
+    let mut projections = projections.to_vec();
+    projections.sort_by_key(|projection| projection.time_ms.unwrap_or(u64::MAX));
+    let width = projections.iter().map(|projection| projection.label().len()).max().unwrap_or(0);
+
+    let mut lines = vec![format!("{:width$}  Time", "Milestone", width = width)];
+    for projection in &projections {
+        let time = match projection.time_ms {
+            Some(time_ms) => format_duration(time_ms / 1000),
+            None => "not reached".to_string(),
+        };
+        lines.push(format!("{:width$}  {}", projection.label(), time, width = width));
+    }
+    lines.join("\n")
+}
+
+// One row per milestone, seconds is empty if it was not reached
+pub fn format_csv(projections: &[Projection]) -> String {
+    let mut lines = vec!["type,name,target,seconds".to_string()];
+    for projection in projections {
+        let (kind, target) = match &projection.milestone {
+            Milestone::JobLevel(_, level) => ("job", level.to_string()),
+            Milestone::Quest(id) => ("quest", id.to_string()),
+        };
+        let seconds = projection.time_ms.map_or(String::new(), |time_ms| (time_ms / 1000).to_string());
+        lines.push(format!("{},\"{}\",{},{}", kind, projection.name.replace('"', "\"\""), target, seconds));
+    }
+    lines.join("\n")
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SimulatorOptions {
+    pub slot: Option<String>,
+    pub plan: Vec<PlanStep>,
+    pub horizon: u64,
+    pub levels: Vec<u8>,
+    pub csv: bool,
+}
+
+// Parses the arguments after the program name
+pub fn parse_args(args: &[String]) -> Result<SimulatorOptions, String> {
+    let mut options = SimulatorOptions {
+        slot: None,
+        plan: vec![],
+        horizon: SIMULATOR_HORIZON_SECS,
+        levels: SIMULATOR_LEVELS.to_vec(),
+        csv: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--simulate" => {}
+            "--csv" => options.csv = true,
+            "--slot" => options.slot = Some(value()?.clone()),
+            "--plan" => options.plan = parse_plan(value()?)?,
+            "--horizon" => options.horizon = parse_duration(value()?)?,
+            "--levels" => {
+                let levels = value()?;
+                options.levels = levels
+                    .split(',')
+                    .map(|level| level.trim().parse().map_err(|_| format!("invalid levels: {}", levels)))
+                    .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(format!("unknown argument: {}", arg)),
+        }
+    }
+
+    if options.plan.is_empty() {
+        return Err("--plan is required".to_string());
+    }
+    if let Some(slot) = &options.slot {
+        if !slots::is_valid_slot_name(slot) {
+            return Err(format!("invalid slot name: {}", slot));
+        }
+    }
+    Ok(options)
+}
+
+// e.g. "1:2h,2:30m,3"
+fn parse_plan(text: &str) -> Result<Vec<PlanStep>, String> {
+    text.split(',')
+        .map(|step| {
+            let (activity_id, duration) = match step.split_once(':') {
+                Some((activity_id, duration)) => (activity_id, Some(parse_duration(duration)?)),
+                None => (step, None),
+            };
+            let activity_id = activity_id.trim().parse().map_err(|_| format!("invalid plan step: {}", step))?;
+            Ok(PlanStep { activity_id, duration })
+        })
+        .collect()
+}
+
+// Runs the projection for the command line and returns the table or CSV
+pub fn run(options: &SimulatorOptions) -> Result<String, String> {
+    let activity_database =
+        load_activity_database().map_err(|e| format!("Error loading activity database: {}", e))?;
+    let quest_database = load_quest_database().map_err(|e| format!("Error loading quest database: {}", e))?;
+    let item_database = load_item_database().map_err(|e| format!("Error loading item database: {}", e))?;
+    let job_database = load_job_database().map_err(|e| format!("Error loading job database: {}", e))?;
+    let xp_curve_database =
+        load_xp_curve_database().map_err(|e| format!("Error loading XP curve database: {}", e))?;
+
+    let (mut player, mut quests) = match &options.slot {
+        Some(slot) => {
+            let save_name = slots::open_slot_path(SAVES_DIRECTORY, slot).map_err(|e| e.to_string())?;
+            let save = save::load(&save_name).map_err(|e| format!("Error loading {}: {}", save_name, e))?;
+            (save.player, save.quests)
+        }
+        None => (Player::new(), vec![]),
+    };
+    player.update_data(&job_database, &xp_curve_database);
+    player.inventory.update_item_data(&item_database);
+    player.update_activity_data(&activity_database);
+    if quests.is_empty() {
+        quests = quest_database.values().map(|quest_data| Quest::new(quest_data.id, false)).collect();
+    }
+
+    let projections = project(
+        &player,
+        &quests,
+        &options.plan,
+        options.horizon,
+        &options.levels,
+        &activity_database,
+        &quest_database,
+    )?;
+    if options.csv {
+        Ok(format_csv(&projections))
+    } else {
+        Ok(format_table(&projections))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::mock::*;
+    use crate::xp_curve::mock::*;
+    use crate::activity::ActivityData;
+    use crate::item::Item;
+    use crate::quest::{Goal, Objective, QuestData, Reward};
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn activity_database() -> ActivityDatabase {
+        let mut activity_database = ActivityDatabase::new();
+        for (id, name, job, item) in [(1, "Woodcutting", WOODCUTTER, 1), (2, "Mining", MINER, 2)] {
+            activity_database.insert(
+                id,
+                ActivityData {
+                    id,
+                    name: name.to_string(),
+                    description: String::new(),
+                    duration_ms: 10_000,
+                    experience: vec![(job, 10)],
+                    items: vec![Item::new(item, 1)],
+                    loot: None,
+                },
+            );
+        }
+        activity_database
+    }
+
+    fn quest_database() -> QuestDatabase {
+        let mut quest_database = QuestDatabase::new();
+        quest_database.insert(
+            1,
+            QuestData {
+                id: 1,
+                name: "Ten Stones".to_string(),
+                description: String::new(),
+                reward: Reward { experience: None, items: None, gold: Some(5) },
+                goal: Goal { objective: Objective::CollectItem(2), required_amount: 10 },
+            },
+        );
+        quest_database
+    }
+
+    #[test]
+    fn test_project() {
+        let plan = vec![
+            PlanStep { activity_id: 1, duration: Some(3600) },
+            PlanStep { activity_id: 2, duration: None },
+        ];
+        let quests = vec![Quest::new(1, false)];
+        let mut player = Player::new();
+        player.update_data(&job_database(), &xp_curve_database());
+
+        let projections =
+            project(&player, &quests, &plan, 86400, &[2], &activity_database(), &quest_database()).unwrap();
+
+        let time = |label: &str| projections.iter().find(|projection| projection.label() == label).unwrap().time_ms;
+        // 110 XP for level 2 is 11 cycles of 10s
+        assert_eq!(time("Woodcutter level 2"), Some(110_000));
+        assert_eq!(time("Miner level 2"), Some(3_600_000 + 110_000));
+        assert_eq!(time("Farmer level 2"), None);
+        assert_eq!(time("Quest: Ten Stones"), Some(3_600_000 + 100_000));
+    }
+
+    #[test]
+    fn test_format_csv() {
+        let projections = vec![
+            Projection {
+                milestone: Milestone::JobLevel(MINER, 5),
+                name: "Miner".to_string(),
+                time_ms: Some(61_500),
+            },
+            Projection { milestone: Milestone::Quest(3), name: "Carrots".to_string(), time_ms: None },
+        ];
+
+        assert_eq!(
+            format_csv(&projections),
+            "type,name,target,seconds\njob,\"Miner\",5,61\nquest,\"Carrots\",3,"
+        );
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse_args(&args("--simulate --plan 1:2h,3 --horizon 7d --levels 5,10 --csv")).unwrap();
+
+        assert_eq!(
+            options.plan,
+            vec![PlanStep { activity_id: 1, duration: Some(7200) }, PlanStep { activity_id: 3, duration: None }]
+        );
+        assert_eq!(options.horizon, 7 * 86400);
+        assert_eq!(options.levels, vec![5, 10]);
+        assert!(options.csv);
+        assert!(parse_args(&args("--simulate --horizon 7d")).is_err());
+        assert!(parse_args(&args("--simulate --plan 1:forever")).is_err());
+    }
+}
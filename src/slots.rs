@@ -0,0 +1,248 @@
+// Save slots are the save files in the saves directory, one per character
+
+use crate::game_state::GameState;
+use crate::ledger::SessionLedger;
+use crate::player::Player;
+use crate::save;
+use crate::save::SaveError;
+use crate::utils::current_timestamp;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SlotMetadata {
+    pub player_level: u8,
+    pub job_levels: Vec<(String, u8)>,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SlotInfo {
+    pub name: String,
+    // the error message if the slot could not be loaded
+    pub metadata: Result<SlotMetadata, String>,
+}
+
+pub fn slot_path(directory: &str, name: &str) -> String {
+    Path::new(directory)
+        .join(format!("{}.json", name))
+        .to_string_lossy()
+        .into_owned()
+}
+
+// The slots sorted by the time they were last played, most recent first. Files whose name
+// can not be opened as a slot are left out.
+pub fn list_slots(directory: &str) -> Result<Vec<SlotInfo>, SaveError> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(SaveError::Io(e)),
+    };
+
+    let mut slots = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) if is_valid_slot_name(name) => name.to_string(),
+            _ => continue,
+        };
+        let metadata = match save::load(&path.to_string_lossy()) {
+            Ok(save) => Ok(SlotMetadata {
+                player_level: save.player.level,
+                job_levels: save
+                    .player
+                    .jobs
+                    .iter()
+                    .map(|job| (job.name.clone(), job.level))
+                    .collect(),
+                timestamp: save.timestamp,
+            }),
+            Err(e) => Err(e.to_string()),
+        };
+        slots.push(SlotInfo { name, metadata });
+    }
+
+    slots.sort_by(|a, b| {
+        let timestamp = |slot: &SlotInfo| slot.metadata.as_ref().map_or(0, |metadata| metadata.timestamp);
+        timestamp(b).cmp(&timestamp(a)).then(a.name.cmp(&b.name))
+    });
+    Ok(slots)
+}
+
+pub fn create_slot(directory: &str, name: &str, backup_count: usize) -> Result<(), SaveError> {
+    check_new_slot(directory, name)?;
+    fs::create_dir_all(directory)?;
+    save::save(
+        &GameState::new(),
+        &Player::with_seed(current_timestamp()),
+        &vec![],
+        &SessionLedger::new(),
+        &slot_path(directory, name),
+        backup_count,
+    )
+}
+
+// Renames the slot along with its backups
+pub fn rename_slot(
+    directory: &str,
+    name: &str,
+    new_name: &str,
+    backup_count: usize,
+) -> Result<(), SaveError> {
+    check_new_slot(directory, new_name)?;
+    let path = slot_path(directory, name);
+    let new_path = slot_path(directory, new_name);
+    fs::rename(&path, &new_path)?;
+    for index in 1..=backup_count {
+        let backup = save::backup_name(&path, index);
+        if Path::new(&backup).exists() {
+            fs::rename(&backup, save::backup_name(&new_path, index))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn duplicate_slot(directory: &str, name: &str, new_name: &str) -> Result<(), SaveError> {
+    check_new_slot(directory, new_name)?;
+    fs::copy(slot_path(directory, name), slot_path(directory, new_name))?;
+    Ok(())
+}
+
+// Deletes the slot along with its backups
+pub fn delete_slot(directory: &str, name: &str, backup_count: usize) -> Result<(), SaveError> {
+    let path = slot_path(directory, name);
+    fs::remove_file(&path)?;
+    for index in 1..=backup_count {
+        let backup = save::backup_name(&path, index);
+        if Path::new(&backup).exists() {
+            fs::remove_file(backup)?;
+        }
+    }
+    Ok(())
+}
+
+// Copies a save from before slots existed, with its backups, into a slot if there are no
+// slots yet
+pub fn import_legacy_save(
+    directory: &str,
+    legacy_path: &str,
+    name: &str,
+    backup_count: usize,
+) -> Result<bool, SaveError> {
+    if !Path::new(legacy_path).exists() || !list_slots(directory)?.is_empty() {
+        return Ok(false);
+    }
+    fs::create_dir_all(directory)?;
+    let path = slot_path(directory, name);
+    fs::copy(legacy_path, &path)?;
+    for index in 1..=backup_count {
+        let backup = save::backup_name(legacy_path, index);
+        if Path::new(&backup).exists() {
+            fs::copy(&backup, save::backup_name(&path, index))?;
+        }
+    }
+    Ok(true)
+}
+
+pub fn is_valid_slot_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name.trim() == name
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_')
+}
+
+// The path of the slot to play, the name is checked so that it can not point outside the
+// directory
+pub fn open_slot_path(directory: &str, name: &str) -> Result<String, SaveError> {
+    check_slot_name(name)?;
+    Ok(slot_path(directory, name))
+}
+
+fn check_slot_name(name: &str) -> Result<(), SaveError> {
+    if !is_valid_slot_name(name) {
+        return Err(SaveError::InvalidSlot(format!(
+            "\"{}\" is not a valid slot name, use letters, numbers, spaces, - and _",
+            name
+        )));
+    }
+    Ok(())
+}
+
+fn check_new_slot(directory: &str, name: &str) -> Result<(), SaveError> {
+    check_slot_name(name)?;
+    if Path::new(&slot_path(directory, name)).exists() {
+        return Err(SaveError::InvalidSlot(format!("a slot named \"{}\" already exists", name)));
+    }
+    Ok(())
+}
+
+mod tests {
+    use super::*;
+
+    fn test_directory(name: &str) -> String {
+        let directory = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        directory.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_create_and_list_slots() {
+        let directory = test_directory("idle_rust_slots_create");
+
+        assert!(list_slots(&directory).unwrap().is_empty());
+        create_slot(&directory, "First", 2).unwrap();
+        create_slot(&directory, "Second", 2).unwrap();
+        assert!(create_slot(&directory, "First", 2).is_err());
+
+        let slots = list_slots(&directory).unwrap();
+        let mut names: Vec<&str> = slots.iter().map(|slot| slot.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["First", "Second"]);
+        fs::copy(slot_path(&directory, "First"), format!("{}/my.save.json", directory)).unwrap();
+        assert_eq!(list_slots(&directory).unwrap().len(), 2);
+        let metadata = slots[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata.player_level, 1);
+        // the jobs are added when the slot is first played
+        assert!(metadata.job_levels.is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_rename_duplicate_and_delete_slots() {
+        let directory = test_directory("idle_rust_slots_manage");
+        create_slot(&directory, "Main", 2).unwrap();
+        // saving again leaves a backup that has to follow the slot around
+        save::save(&GameState::new(), &Player::new(), &vec![], &SessionLedger::new(), &slot_path(&directory, "Main"), 2).unwrap();
+
+        rename_slot(&directory, "Main", "Renamed", 2).unwrap();
+        assert!(!Path::new(&slot_path(&directory, "Main")).exists());
+        assert!(Path::new(&save::backup_name(&slot_path(&directory, "Renamed"), 1)).exists());
+
+        duplicate_slot(&directory, "Renamed", "Copy").unwrap();
+        assert_eq!(list_slots(&directory).unwrap().len(), 2);
+
+        delete_slot(&directory, "Renamed", 2).unwrap();
+        let slots = list_slots(&directory).unwrap();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].name, "Copy");
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_slot_names() {
+        assert!(is_valid_slot_name("My Farmer_2-b"));
+        assert!(!is_valid_slot_name(""));
+        assert!(!is_valid_slot_name(" padded "));
+        assert!(!is_valid_slot_name("../escape"));
+        assert!(!is_valid_slot_name("a.json"));
+        assert!(open_slot_path("saves", "../escape").is_err());
+        assert_eq!(open_slot_path("saves", "Main").unwrap(), slot_path("saves", "Main"));
+    }
+}
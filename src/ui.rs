@@ -1,24 +1,111 @@
 use crate::game_state::GameState;
+use crate::constants::BACKGROUND_REPAINT_INTERVAL_MS;
+use crate::constants::SIMULATION_TICK_MS;
+use crate::constants::PRESTIGE_BONUS_PERCENT;
+use crate::constants::PRESTIGE_MIN_LEVEL;
 use crate::player::Player;
 use eframe::egui;
 use crate::utils::ItemDatabase;
 use crate::utils::QuestDatabase;
+use crate::utils::ActivityDatabase;
+use crate::utils::Databases;
+use crate::utils::RecipeDatabase;
+use crate::utils::VendorDatabase;
+use crate::crafting::RecipeData;
+use crate::equipment::EquipmentSlot;
+use crate::item::ItemCategory;
+use crate::item::ItemData;
+use crate::item::Rarity;
 use crate::quest::Quest;
 use crate::quest::QuestData;
 use crate::game_state::QuestState;
+use crate::inventory::InventorySort;
+use crate::save::LoadFailure;
+use crate::slots::SlotInfo;
+use crate::offline::OfflineReport;
+use crate::utils::current_timestamp;
+use crate::utils::format_duration;
+use crate::utils::format_items;
+use std::collections::HashMap;
+
+// State of the widgets that has to persist between frames but is not saved
+pub struct UiState {
+    pub inventory_search: String,
+    pub inventory_sort: InventorySort,
+    pub inventory_ascending: bool,
+    // None shows every category
+    pub inventory_category: Option<ItemCategory>,
+    pub selected_item: Option<u128>,
+    // loaded the first time they are shown, None when the icon could not be loaded
+    pub item_icons: HashMap<u128, Option<egui::TextureHandle>>,
+    // job waiting for the prestige to be confirmed
+    pub confirm_prestige: Option<u128>,
+    pub selected_vendor: Option<u128>,
+}
+
+impl UiState {
+    pub fn new() -> Self {
+        Self {
+            inventory_search: String::new(),
+            inventory_sort: InventorySort::Name,
+            inventory_ascending: true,
+            inventory_category: None,
+            selected_item: None,
+            item_icons: HashMap::new(),
+            confirm_prestige: None,
+            selected_vendor: None,
+        }
+    }
+}
+
+pub struct SlotPickerState {
+    pub selected: Option<String>,
+    pub new_name: String,
+    pub confirm_delete: bool,
+    pub error: Option<String>,
+}
+
+impl SlotPickerState {
+    pub fn new() -> Self {
+        Self {
+            selected: None,
+            new_name: String::new(),
+            confirm_delete: false,
+            error: None,
+        }
+    }
+}
+
 pub enum ButtonClicked {
     Activity,
     Crafting,
     Inventory,
     Stats,
     Jobs,
-    Mining,
-    Woodcutting,
-    Farming,
+    StartActivity(u128),
     Quest,
     AvailableQuests,
     CompletedQuests,
     QuestCompleteClicked(u128),
+    Craft(u128, u32),
+    CancelCrafting,
+    StartNewGame,
+    ContinueFromBackup,
+    QuitWithoutSaving,
+    OpenSlot(String),
+    CreateSlot(String),
+    RenameSlot(String, String),
+    DuplicateSlot(String, String),
+    DeleteSlot(String),
+    DismissOfflineReport,
+    Prestige,
+    PrestigeJob(u128),
+    Equip(u128),
+    Unequip(EquipmentSlot),
+    Shop,
+    // vendor id, item id and quantity
+    Buy(u128, u128, u128),
+    Sell(u128, u128, u128),
 }
 
 pub fn update(
@@ -26,10 +113,12 @@ pub fn update(
     ctx: &egui::Context,
     game_state: &GameState,
     quests: &Vec<Quest>,
-    item_database: &ItemDatabase,
-    quest_database: &QuestDatabase,
+    databases: &Databases,
+    ui_state: &mut UiState,
+    now: u64,
 ) -> Option<ButtonClicked> {
     let mut button_clicked: Option<ButtonClicked> = None; // Initialize event variable
+    let item_database = &databases.items;
 
     egui::CentralPanel::default().show(ctx, |ui| {
         button_clicked = show_header_ui(ui, game_state);
@@ -37,32 +126,263 @@ pub fn update(
         if button_clicked.is_none() {
             match game_state {
                 GameState::Activity => {
-                    button_clicked = show_activity_ui(ui, player, item_database);
+                    button_clicked = show_activity_ui(ui, player, item_database, &databases.activities);
                 }
                 GameState::Crafting => {
-                    button_clicked = show_crafting_ui(ui, player, item_database);
+                    button_clicked = show_crafting_ui(ui, player, item_database, &databases.recipes);
                 }
                 GameState::Inventory => {
-                    button_clicked = show_inventory_ui(ui, player, item_database);
+                    button_clicked = show_inventory_ui(ui, player, item_database, ui_state);
                 }
                 GameState::Quest(QuestState::Available) => {
-                    button_clicked = show_available_quests_ui(ui, quests, quest_database, item_database, player);
+                    button_clicked = show_available_quests_ui(ui, quests, &databases.quests, item_database, player);
                 }
                 GameState::Quest(QuestState::Completed) => {
-                    button_clicked = show_completed_quests_ui(ui, quests, quest_database, item_database, player);
+                    button_clicked = show_completed_quests_ui(ui, quests, &databases.quests, item_database, player);
+                }
+                GameState::Prestige => {
+                    button_clicked = show_prestige_ui(ui, player, ui_state);
+                }
+                GameState::Shop => {
+                    button_clicked = show_shop_ui(ui, player, item_database, &databases.vendors, ui_state, now);
                 }
-                _ => {}
             }
         }
     });
 
-    // Request a repaint for the next frame - needed for continuous updates
-    ctx.request_repaint();
+    // The game keeps track of time on its own, so repainting only needs to keep the progress
+    // bars moving. Nothing changes faster than a simulation tick, and much less often matters
+    // when the window is in the background.
+    let repaint_interval_ms = if ctx.input(|i| i.focused) {
+        SIMULATION_TICK_MS
+    } else {
+        BACKGROUND_REPAINT_INTERVAL_MS
+    };
+    ctx.request_repaint_after(std::time::Duration::from_millis(repaint_interval_ms));
 
     // Return the event captured during UI drawing
     button_clicked
 }
 
+pub fn show_slot_picker(
+    ctx: &egui::Context,
+    slots: &[SlotInfo],
+    state: &mut SlotPickerState,
+) -> Option<ButtonClicked> {
+    let mut button_clicked = None;
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading("Choose a Save Slot");
+        ui.separator();
+
+        if let Some(error) = &state.error {
+            ui.label(egui::RichText::new(error).color(egui::Color32::RED));
+            ui.separator();
+        }
+
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 80.0)
+            .show(ui, |ui| {
+                if slots.is_empty() {
+                    ui.label("No save slots yet, create one below.");
+                }
+                for slot in slots {
+                    if slot_ui_component(ui, slot, state.selected.as_deref() == Some(slot.name.as_str())) {
+                        state.selected = Some(slot.name.clone());
+                        state.confirm_delete = false;
+                    }
+                }
+            });
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut state.new_name);
+            if ui.button("New Slot").clicked() {
+                button_clicked = Some(ButtonClicked::CreateSlot(state.new_name.clone()));
+            }
+        });
+
+        let selected = match &state.selected {
+            Some(selected) => selected.clone(),
+            None => return,
+        };
+        ui.horizontal(|ui| {
+            if ui.button(format!("Play {}", selected)).clicked() {
+                button_clicked = Some(ButtonClicked::OpenSlot(selected.clone()));
+            }
+            if ui.button("Rename to Name").clicked() {
+                button_clicked = Some(ButtonClicked::RenameSlot(selected.clone(), state.new_name.clone()));
+            }
+            if ui.button("Duplicate as Name").clicked() {
+                button_clicked = Some(ButtonClicked::DuplicateSlot(selected.clone(), state.new_name.clone()));
+            }
+            if state.confirm_delete {
+                if ui.button(egui::RichText::new("Confirm Delete").color(egui::Color32::RED)).clicked() {
+                    button_clicked = Some(ButtonClicked::DeleteSlot(selected.clone()));
+                    state.confirm_delete = false;
+                }
+            } else if ui.button("Delete").clicked() {
+                state.confirm_delete = true;
+            }
+        });
+    });
+
+    button_clicked
+}
+
+// Returns true if the slot was clicked
+fn slot_ui_component(ui: &mut egui::Ui, slot: &SlotInfo, is_selected: bool) -> bool {
+    let mut clicked = false;
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_min_width(ui.available_width() * 0.9);
+        if ui
+            .selectable_label(is_selected, egui::RichText::new(&slot.name).strong())
+            .clicked()
+        {
+            clicked = true;
+        }
+        match &slot.metadata {
+            Ok(metadata) => {
+                let job_levels: Vec<String> = metadata
+                    .job_levels
+                    .iter()
+                    .map(|(job, level)| format!("{} {}", job, level))
+                    .collect();
+                ui.label(format!("Level {} | {}", metadata.player_level, job_levels.join(", ")));
+                let last_played = current_timestamp().saturating_sub(metadata.timestamp);
+                ui.label(format!("Last played {} ago", format_duration(last_played)));
+            }
+            Err(e) => {
+                ui.label(egui::RichText::new(e).color(egui::Color32::RED));
+            }
+        }
+    });
+    clicked
+}
+
+pub fn show_offline_report_dialog(
+    ctx: &egui::Context,
+    report: &OfflineReport,
+    item_database: &ItemDatabase,
+) -> Option<ButtonClicked> {
+    let mut button_clicked = None;
+
+    egui::Window::new("Welcome Back!")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            if let Some(clock_anomaly) = &report.clock_anomaly {
+                ui.label(egui::RichText::new(clock_anomaly.to_string()).color(egui::Color32::YELLOW));
+            }
+            ui.label(format!("You were away for {}.", format_duration(report.time_elapsed)));
+            if report.was_capped() {
+                ui.label(format!(
+                    "Offline progress is capped at {}.",
+                    format_duration(report.time_capped)
+                ));
+            }
+            if report.efficiency_percent() < 100 {
+                ui.label(format!(
+                    "Offline efficiency: {}% ({} of progress).",
+                    report.efficiency_percent(),
+                    format_duration(report.effective_ms / 1000)
+                ));
+            }
+            ui.separator();
+
+            if let Some(activity) = &report.activity {
+                ui.label(format!("{}: {} cycles completed", activity, report.cycles));
+            }
+            if let Some(crafting) = &report.crafting {
+                ui.label(format!("Crafting {}: {} crafted", crafting, report.crafts));
+            }
+
+            if !report.experience.is_empty() {
+                ui.add_space(4.0);
+                ui.label(egui::RichText::new("Experience").strong());
+                for (job, experience) in &report.experience {
+                    let levels = report
+                        .levels_gained
+                        .iter()
+                        .find(|(leveled_job, _)| leveled_job == job)
+                        .map_or(String::new(), |(_, levels)| {
+                            format!(" (+{} level{})", levels, if *levels == 1 { "" } else { "s" })
+                        });
+                    ui.label(format!("  {}: +{} XP{}", job, experience, levels));
+                }
+                if report.player_levels_gained > 0 {
+                    ui.label(format!("  {}", report.player_level_line()));
+                }
+            }
+
+            if !report.items_gained.is_empty() {
+                ui.add_space(4.0);
+                ui.label(egui::RichText::new("Items Gained").strong());
+                ui.label(format!("  {}", format_items(&report.items_gained, item_database)));
+            }
+            if !report.items_used.is_empty() {
+                ui.add_space(4.0);
+                ui.label(egui::RichText::new("Items Used").strong());
+                ui.label(format!("  {}", format_items(&report.items_used, item_database)));
+            }
+
+            ui.add_space(8.0);
+            if ui.button("Continue").clicked() {
+                button_clicked = Some(ButtonClicked::DismissOfflineReport);
+            }
+        });
+
+    button_clicked
+}
+
+pub fn show_load_failure_dialog(ctx: &egui::Context, load_failure: &LoadFailure) -> Option<ButtonClicked> {
+    let mut button_clicked = None;
+
+    egui::CentralPanel::default().show(ctx, |_ui| {});
+    egui::Window::new("Your save could not be loaded")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(load_failure.error.to_string());
+            ui.add_space(4.0);
+            if let Some(backup_name) = &load_failure.backup_name {
+                ui.label(format!("A copy of the save was kept in {}.", backup_name));
+            }
+            let safe_to_overwrite = load_failure.is_safe_to_overwrite();
+            match &load_failure.restored_from {
+                Some(restored_from) => {
+                    ui.label(format!("Your progress was restored from the backup {}.", restored_from));
+                }
+                None if safe_to_overwrite => {
+                    ui.label("Starting a new game will replace the save when the game exits.");
+                }
+                None => {}
+            }
+            if !safe_to_overwrite {
+                ui.label(egui::RichText::new("The save could not be backed up.").color(egui::Color32::RED));
+                ui.label("To protect it, progress in this session will not be saved.");
+            }
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                if load_failure.restored_from.is_some() {
+                    if ui.button("Continue").clicked() {
+                        button_clicked = Some(ButtonClicked::ContinueFromBackup);
+                    }
+                } else if ui.button("Start New Game").clicked() {
+                    button_clicked = Some(ButtonClicked::StartNewGame);
+                }
+                if ui.button("Quit Without Saving").clicked() {
+                    button_clicked = Some(ButtonClicked::QuitWithoutSaving);
+                }
+            });
+        });
+
+    button_clicked
+}
+
 fn show_header_ui(ui: &mut egui::Ui, game_state: &GameState) -> Option<ButtonClicked> {
     let mut button_clicked = None;
 
@@ -83,6 +403,12 @@ fn show_header_ui(ui: &mut egui::Ui, game_state: &GameState) -> Option<ButtonCli
                 if ui.button("Quest").clicked() {
                     button_clicked = Some(ButtonClicked::Quest);
                 }
+                if ui.button("Prestige").clicked() {
+                    button_clicked = Some(ButtonClicked::Prestige);
+                }
+                if ui.button("Shop").clicked() {
+                    button_clicked = Some(ButtonClicked::Shop);
+                }
             }
             GameState::Crafting => {
                 if ui.button("Activity").clicked() {
@@ -95,6 +421,12 @@ fn show_header_ui(ui: &mut egui::Ui, game_state: &GameState) -> Option<ButtonCli
                 if ui.button("Quest").clicked() {
                     button_clicked = Some(ButtonClicked::Quest);
                 }
+                if ui.button("Prestige").clicked() {
+                    button_clicked = Some(ButtonClicked::Prestige);
+                }
+                if ui.button("Shop").clicked() {
+                    button_clicked = Some(ButtonClicked::Shop);
+                }
             }
             GameState::Inventory => {
                 if ui.button("Activity").clicked() {
@@ -107,6 +439,12 @@ fn show_header_ui(ui: &mut egui::Ui, game_state: &GameState) -> Option<ButtonCli
                 if ui.button("Quest").clicked() {
                     button_clicked = Some(ButtonClicked::Quest);
                 }
+                if ui.button("Prestige").clicked() {
+                    button_clicked = Some(ButtonClicked::Prestige);
+                }
+                if ui.button("Shop").clicked() {
+                    button_clicked = Some(ButtonClicked::Shop);
+                }
             }
             GameState::Quest(_) => {
                 if ui.button("Activity").clicked() {
@@ -119,6 +457,48 @@ fn show_header_ui(ui: &mut egui::Ui, game_state: &GameState) -> Option<ButtonCli
                     button_clicked = Some(ButtonClicked::Inventory);
                 }
                 ui.add_enabled(false, egui::Button::new("Quest"));
+                if ui.button("Prestige").clicked() {
+                    button_clicked = Some(ButtonClicked::Prestige);
+                }
+                if ui.button("Shop").clicked() {
+                    button_clicked = Some(ButtonClicked::Shop);
+                }
+            }
+            GameState::Prestige => {
+                if ui.button("Activity").clicked() {
+                    button_clicked = Some(ButtonClicked::Activity);
+                }
+                if ui.button("Crafting").clicked() {
+                    button_clicked = Some(ButtonClicked::Crafting);
+                }
+                if ui.button("Inventory").clicked() {
+                    button_clicked = Some(ButtonClicked::Inventory);
+                }
+                if ui.button("Quest").clicked() {
+                    button_clicked = Some(ButtonClicked::Quest);
+                }
+                ui.add_enabled(false, egui::Button::new("Prestige"));
+                if ui.button("Shop").clicked() {
+                    button_clicked = Some(ButtonClicked::Shop);
+                }
+            }
+            GameState::Shop => {
+                if ui.button("Activity").clicked() {
+                    button_clicked = Some(ButtonClicked::Activity);
+                }
+                if ui.button("Crafting").clicked() {
+                    button_clicked = Some(ButtonClicked::Crafting);
+                }
+                if ui.button("Inventory").clicked() {
+                    button_clicked = Some(ButtonClicked::Inventory);
+                }
+                if ui.button("Quest").clicked() {
+                    button_clicked = Some(ButtonClicked::Quest);
+                }
+                if ui.button("Prestige").clicked() {
+                    button_clicked = Some(ButtonClicked::Prestige);
+                }
+                ui.add_enabled(false, egui::Button::new("Shop"));
             }
         }
     });
@@ -126,10 +506,139 @@ fn show_header_ui(ui: &mut egui::Ui, game_state: &GameState) -> Option<ButtonCli
     button_clicked
 }
 
+fn show_prestige_ui(ui: &mut egui::Ui, player: &Player, ui_state: &mut UiState) -> Option<ButtonClicked> {
+    let mut button_clicked = None;
+
+    ui.label(format!(
+        "A job that reached level {} can be prestiged. It starts over at level 1 and loses its perks \
+         until it levels up again, but every prestige permanently gives the activities that train it \
+         {}% more XP and {}% more chance of bonus items. Your player level is kept.",
+        PRESTIGE_MIN_LEVEL, PRESTIGE_BONUS_PERCENT, PRESTIGE_BONUS_PERCENT
+    ));
+    ui.add_space(8.0);
+
+    for job in &player.jobs {
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.set_min_width(ui.available_width() * 0.9);
+
+            ui.label(egui::RichText::new(format!("{} {}", job.icon, job.name)).strong());
+            ui.separator();
+            ui.label(format!("Level: {} | Prestige: {}", job.level, job.prestige));
+            ui.label(format!("Current bonus: +{}% XP, +{}% bonus item chance", job.prestige_bonus_percent(), job.prestige_bonus_percent()));
+            let next_bonus = job.prestige_bonus_percent() + PRESTIGE_BONUS_PERCENT;
+            ui.label(format!("After prestige: +{}% XP, +{}% bonus item chance", next_bonus, next_bonus));
+            ui.add_space(4.0);
+
+            if ui_state.confirm_prestige == Some(job.id) {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Reset {} to level 1?", job.name));
+                    if ui.button("Confirm").clicked() {
+                        button_clicked = Some(ButtonClicked::PrestigeJob(job.id));
+                        ui_state.confirm_prestige = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        ui_state.confirm_prestige = None;
+                    }
+                });
+            } else if ui.add_enabled(job.can_prestige(), egui::Button::new("Prestige")).clicked() {
+                ui_state.confirm_prestige = Some(job.id);
+            }
+            if !job.can_prestige() {
+                ui.label(format!("Requires level {}", PRESTIGE_MIN_LEVEL));
+            }
+        });
+        ui.add_space(5.0);
+    }
+
+    button_clicked
+}
+
+fn show_shop_ui(
+    ui: &mut egui::Ui,
+    player: &Player,
+    item_database: &ItemDatabase,
+    vendor_database: &VendorDatabase,
+    ui_state: &mut UiState,
+    // by the ledger, so the stock does not restock when the clock is changed
+    now: u64,
+) -> Option<ButtonClicked> {
+    let mut button_clicked = None;
+
+    // One tab per vendor, sorted by id so the order is stable
+    let mut vendors: Vec<_> = vendor_database.values().collect();
+    vendors.sort_by_key(|vendor| vendor.id);
+    ui.horizontal(|ui| {
+        for vendor in &vendors {
+            let is_selected = ui_state.selected_vendor == Some(vendor.id);
+            if ui.selectable_label(is_selected, &vendor.name).clicked() {
+                ui_state.selected_vendor = Some(vendor.id);
+            }
+        }
+    });
+    let vendor = match ui_state.selected_vendor.and_then(|id| vendor_database.get(&id)).or(vendors.first().copied()) {
+        Some(vendor) => vendor,
+        None => {
+            ui.label("No vendors.");
+            return None;
+        }
+    };
+    let item_name = |id: u128| item_database.get(&id).map_or("Unknown Item", |d| d.name.as_str());
+
+    ui.label(&vendor.description);
+    ui.label(format!("Gold: {}", player.inventory.gold));
+    ui.separator();
+
+    ui.columns(2, |columns| {
+        columns[0].label(egui::RichText::new("Buy").strong());
+        for stock in &vendor.stock {
+            let remaining = player.shop.remaining(vendor.id, stock, now);
+            let ui = &mut columns[0];
+            ui.horizontal(|ui| {
+                ui.label(format!("{} for {} gold ({} left)", item_name(stock.item_id), stock.price, remaining));
+                for quantity in [1, 10] {
+                    let enabled = quantity <= remaining && stock.price * quantity <= player.inventory.gold;
+                    if ui.add_enabled(enabled, egui::Button::new(format!("x{}", quantity))).clicked() {
+                        button_clicked = Some(ButtonClicked::Buy(vendor.id, stock.item_id, quantity));
+                    }
+                }
+            });
+            if let Some(restocks_at) = player.shop.restocks_at(vendor.id, stock, now) {
+                ui.label(egui::RichText::new(format!("  restocks in {}", format_duration(restocks_at - now))).weak());
+            }
+        }
+
+        columns[1].label(egui::RichText::new("Sell").strong());
+        let items: Vec<_> = player
+            .inventory
+            .list_items(item_database, "", None, InventorySort::Name, true)
+            .into_iter()
+            .filter_map(|item| item_database.get(&item.id).map(|item_data| (item, item_data)))
+            .filter(|(_, item_data)| vendor.buys_item(item_data))
+            .collect();
+        if items.is_empty() {
+            columns[1].label("(Nothing this vendor buys)");
+        }
+        for (item, item_data) in items {
+            columns[1].horizontal(|ui| {
+                ui.label(format!("{} x{} for {} gold each", item_data.name, item.quantity, item_data.sell_value));
+                if ui.button("x1").clicked() {
+                    button_clicked = Some(ButtonClicked::Sell(vendor.id, item.id, 1));
+                }
+                if ui.button("All").clicked() {
+                    button_clicked = Some(ButtonClicked::Sell(vendor.id, item.id, item.quantity));
+                }
+            });
+        }
+    });
+
+    button_clicked
+}
+
 fn show_activity_ui(
     ui: &mut egui::Ui,
     player: &mut Player,
     item_database: &ItemDatabase,
+    activity_database: &ActivityDatabase,
 ) -> Option<ButtonClicked> {
     let mut button_clicked = None; // Initialize as None
 
@@ -139,7 +648,7 @@ fn show_activity_ui(
         "Current Activity: {}",
         match current_activity {
             Some(act) => {
-                format!("{:?}", act.name)
+                act.name.clone()
             }
             None => {
                 "Nothing".to_string()
@@ -148,7 +657,7 @@ fn show_activity_ui(
     ));
 
     if let Some(act) = current_activity {
-        ui.add(egui::ProgressBar::new(act.timer / act.duration));
+        ui.add(egui::ProgressBar::new(act.progress(&player.jobs, &player.equipment)));
     } else {
         ui.add(egui::ProgressBar::new(0.0));
     }
@@ -159,41 +668,337 @@ fn show_activity_ui(
     ui.separator();
     ui.label("Choose Activity:");
 
-    // Check buttons and store the choice if clicked
-    if ui.button("Mining").clicked() {
-        button_clicked = Some(ButtonClicked::Mining);
-    }
-    if ui.button("Woodcutting").clicked() {
-        button_clicked = Some(ButtonClicked::Woodcutting);
-    }
-    if ui.button("Farming").clicked() {
-        button_clicked = Some(ButtonClicked::Farming);
+    // One button per activity in the database, sorted by id so the order is stable
+    let mut activities: Vec<_> = activity_database.values().collect();
+    activities.sort_by_key(|activity_data| activity_data.id);
+    for activity_data in activities {
+        if ui
+            .button(&activity_data.name)
+            .on_hover_text(&activity_data.description)
+            .clicked()
+        {
+            button_clicked = Some(ButtonClicked::StartActivity(activity_data.id));
+        }
     }
-    // Add more buttons for other occupations...
 
     button_clicked // Return the result (None if no button clicked)
 }
 
 fn show_crafting_ui(
     ui: &mut egui::Ui,
-    player: &mut Player,
+    player: &Player,
     item_database: &ItemDatabase,
+    recipe_database: &RecipeDatabase,
 ) -> Option<ButtonClicked> {
     let mut button_clicked = None;
 
+    match player.get_crafting() {
+        Some(crafting) => {
+            ui.label(format!("Currently Crafting: {}", crafting));
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::ProgressBar::new(crafting.progress())
+                        .desired_width(ui.available_width() * 0.8),
+                );
+                if ui.button("Cancel").clicked() {
+                    button_clicked = Some(ButtonClicked::CancelCrafting);
+                }
+            });
+        }
+        None => {
+            ui.label("Currently Crafting: Nothing");
+            ui.add(egui::ProgressBar::new(0.0));
+        }
+    }
+    ui.separator();
+
+    let mut recipes: Vec<_> = recipe_database.values().collect();
+    recipes.sort_by_key(|recipe_data| recipe_data.id);
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for recipe_data in recipes {
+            if let Some(quantity) = recipe_ui_component(ui, recipe_data, item_database, player) {
+                button_clicked = Some(ButtonClicked::Craft(recipe_data.id, quantity));
+            }
+        }
+    });
+
     button_clicked
 }
 
+// Returns the quantity to craft if one of the craft buttons was clicked
+fn recipe_ui_component(
+    ui: &mut egui::Ui,
+    recipe_data: &RecipeData,
+    item_database: &ItemDatabase,
+    player: &Player,
+) -> Option<u32> {
+    let mut quantity = None;
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_min_width(ui.available_width() * 0.9);
+
+        ui.label(egui::RichText::new(&recipe_data.name).strong());
+        ui.separator();
+
+        ui.label(&recipe_data.description);
+        ui.add_space(4.0);
+
+        let current_level = player.get_job(recipe_data.job).map_or(0, |job| job.level);
+        let requirement = format!(
+            "Requires: {} level {} (current: {})",
+            player.job_name(recipe_data.job), recipe_data.required_level, current_level
+        );
+        if recipe_data.has_required_level(&player.jobs) {
+            ui.label(requirement);
+        } else {
+            ui.label(egui::RichText::new(requirement).color(egui::Color32::RED));
+        }
+
+        let inputs: Vec<String> = recipe_data
+            .inputs
+            .iter()
+            .map(|input| {
+                let item_name = item_database.get(&input.id).map_or("Unknown Item", |d| d.name.as_str());
+                let owned = player.inventory.get_item_quantity(input.id);
+                format!("{}x {} ({}/{})", input.quantity, item_name, owned, input.quantity)
+            })
+            .collect();
+        ui.label(format!("Inputs: {}", inputs.join(", ")));
+        ui.label(format!("Outputs: {}", format_items(&recipe_data.outputs, item_database)));
+        ui.label(format!(
+            "Time: {}s | {} XP: {}",
+            recipe_data.duration_ms as f32 / 1000.0,
+            player.job_name(recipe_data.job),
+            recipe_data.experience
+        ));
+
+        let can_craft = recipe_data.can_craft(&player.jobs, &player.inventory);
+        let max_craftable = recipe_data.max_craftable(&player.inventory);
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(can_craft, egui::Button::new("Craft")).clicked() {
+                quantity = Some(1);
+            }
+            if ui
+                .add_enabled(can_craft, egui::Button::new(format!("Craft All ({})", max_craftable)))
+                .clicked()
+            {
+                quantity = Some(max_craftable);
+            }
+        });
+    });
+    ui.add_space(5.0);
+    quantity
+}
+
 fn show_inventory_ui(
     ui: &mut egui::Ui,
-    player: &mut Player,
+    player: &Player,
     item_database: &ItemDatabase,
+    ui_state: &mut UiState,
 ) -> Option<ButtonClicked> {
+    let mut button_clicked = show_equipment_ui(ui, player);
+    ui.separator();
+    ui.label(format!("Gold: {}", player.inventory.gold));
+
+    ui.horizontal(|ui| {
+        ui.label("Search:");
+        ui.text_edit_singleline(&mut ui_state.inventory_search);
+
+        egui::ComboBox::from_label("Sort by")
+            .selected_text(ui_state.inventory_sort.to_string())
+            .show_ui(ui, |ui| {
+                for sort in [InventorySort::Name, InventorySort::Quantity, InventorySort::Id] {
+                    ui.selectable_value(&mut ui_state.inventory_sort, sort, sort.to_string());
+                }
+            });
+
+        let category_text = ui_state.inventory_category.map_or("All".to_string(), |category| category.to_string());
+        egui::ComboBox::from_label("Category").selected_text(category_text).show_ui(ui, |ui| {
+            ui.selectable_value(&mut ui_state.inventory_category, None, "All");
+            for category in [
+                ItemCategory::Resource,
+                ItemCategory::Material,
+                ItemCategory::Food,
+                ItemCategory::Tool,
+                ItemCategory::Armor,
+                ItemCategory::Quest,
+            ] {
+                ui.selectable_value(&mut ui_state.inventory_category, Some(category), category.to_string());
+            }
+        });
+
+        let order = if ui_state.inventory_ascending { "Ascending" } else { "Descending" };
+        if ui.button(order).clicked() {
+            ui_state.inventory_ascending = !ui_state.inventory_ascending;
+        }
+    });
+    ui.separator();
+
+    let items = player.inventory.list_items(
+        item_database,
+        &ui_state.inventory_search,
+        ui_state.inventory_category,
+        ui_state.inventory_sort,
+        ui_state.inventory_ascending,
+    );
+
+    ui.columns(2, |columns| {
+        egui::ScrollArea::vertical().show(&mut columns[0], |ui| {
+            if items.is_empty() {
+                ui.label("(Empty)");
+            }
+            for item in &items {
+                let item_data = item_database.get(&item.id);
+                let is_selected = ui_state.selected_item == Some(item.id);
+                ui.horizontal(|ui| {
+                    let text = match item_data {
+                        Some(item_data) => {
+                            if let Some(icon) = item_icon(ui.ctx(), &mut ui_state.item_icons, item_data) {
+                                ui.add(egui::Image::new((icon.id(), egui::vec2(16.0, 16.0))));
+                            }
+                            egui::RichText::new(format!("{} x{}", item_data.name, item.quantity))
+                                .color(rarity_color(ui, item_data.rarity))
+                        }
+                        None => egui::RichText::new(format!("Unknown Item x{}", item.quantity)),
+                    };
+                    if ui.selectable_label(is_selected, text).clicked() {
+                        ui_state.selected_item = Some(item.id);
+                    }
+                });
+            }
+        });
+
+        let selected_item = ui_state.selected_item;
+        if let Some(clicked) =
+            show_item_details_ui(&mut columns[1], player, item_database, &mut ui_state.item_icons, selected_item)
+        {
+            button_clicked = Some(clicked);
+        }
+    });
+
+    button_clicked
+}
+
+fn show_equipment_ui(ui: &mut egui::Ui, player: &Player) -> Option<ButtonClicked> {
     let mut button_clicked = None;
 
+    ui.label("Equipment:");
+    for slot in [EquipmentSlot::Axe, EquipmentSlot::Pickaxe, EquipmentSlot::Hoe, EquipmentSlot::Armor] {
+        ui.horizontal(|ui| match player.get_equipment(slot) {
+            Some(equipment) => {
+                let effects: Vec<String> = equipment.effects.iter().map(|effect| effect.to_string()).collect();
+                ui.label(format!("{}: {} ({})", slot, equipment.name, effects.join(", ")));
+                if ui.button("Unequip").clicked() {
+                    button_clicked = Some(ButtonClicked::Unequip(slot));
+                }
+            }
+            None => {
+                ui.label(egui::RichText::new(format!("{}: (Empty)", slot)).weak());
+            }
+        });
+    }
+
     button_clicked
 }
 
+fn show_item_details_ui(
+    ui: &mut egui::Ui,
+    player: &Player,
+    item_database: &ItemDatabase,
+    item_icons: &mut HashMap<u128, Option<egui::TextureHandle>>,
+    selected_item: Option<u128>,
+) -> Option<ButtonClicked> {
+    let mut button_clicked = None;
+    let item = match selected_item.and_then(|id| player.inventory.get_item(id)) {
+        Some(item) => item,
+        None => {
+            ui.label("Select an item to see its details.");
+            return None;
+        }
+    };
+
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_min_width(ui.available_width());
+        match item_database.get(&item.id) {
+            Some(item_data) => {
+                ui.horizontal(|ui| {
+                    if let Some(icon) = item_icon(ui.ctx(), item_icons, item_data) {
+                        ui.add(egui::Image::new((icon.id(), egui::vec2(32.0, 32.0))));
+                    }
+                    ui.label(egui::RichText::new(&item_data.name).strong().color(rarity_color(ui, item_data.rarity)));
+                });
+                ui.separator();
+                ui.label(&item_data.description);
+                ui.add_space(4.0);
+                ui.label(format!("{} {}", item_data.rarity, item_data.category));
+                if !item_data.tags.is_empty() {
+                    ui.label(format!("Tags: {}", item_data.tags.join(", ")));
+                }
+                ui.label(format!("Sell value: {} gold", item_data.sell_value));
+                if let Some(stack_limit) = item_data.stack_limit {
+                    ui.label(format!("Stack limit: {}", stack_limit));
+                }
+                if let Some(equipment) = &item_data.equipment {
+                    ui.add_space(4.0);
+                    ui.label(format!("Slot: {}", equipment.slot));
+                    for effect in &equipment.effects {
+                        ui.label(format!("  {}", effect));
+                    }
+                    if ui.button("Equip").clicked() {
+                        button_clicked = Some(ButtonClicked::Equip(item.id));
+                    }
+                }
+            }
+            None => {
+                ui.label(egui::RichText::new("Unknown Item").strong());
+                ui.separator();
+            }
+        }
+        ui.add_space(4.0);
+        ui.label(format!("Id: {}", item.id));
+        ui.label(format!("Quantity: {}", item.quantity));
+    });
+
+    button_clicked
+}
+
+fn rarity_color(ui: &egui::Ui, rarity: Rarity) -> egui::Color32 {
+    match rarity {
+        Rarity::Common => ui.visuals().text_color(),
+        Rarity::Uncommon => egui::Color32::from_rgb(60, 170, 80),
+        Rarity::Rare => egui::Color32::from_rgb(70, 130, 230),
+        Rarity::Epic => egui::Color32::from_rgb(170, 90, 220),
+        Rarity::Legendary => egui::Color32::GOLD,
+    }
+}
+
+// An icon that fails to load is reported once and not tried again
+fn item_icon(
+    ctx: &egui::Context,
+    item_icons: &mut HashMap<u128, Option<egui::TextureHandle>>,
+    item_data: &ItemData,
+) -> Option<egui::TextureHandle> {
+    let path = item_data.icon.as_ref()?;
+    item_icons
+        .entry(item_data.id)
+        .or_insert_with(|| match load_icon(ctx, path) {
+            Ok(texture) => Some(texture),
+            Err(e) => {
+                println!("Error loading icon {}: {}", path, e);
+                None
+            }
+        })
+        .clone()
+}
+
+fn load_icon(ctx: &egui::Context, path: &str) -> Result<egui::TextureHandle, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    let icon = eframe::icon_data::from_png_bytes(&bytes).map_err(|e| e.to_string())?;
+    let image = egui::ColorImage::from_rgba_unmultiplied([icon.width as usize, icon.height as usize], &icon.rgba);
+    Ok(ctx.load_texture(path, image, egui::TextureOptions::default()))
+}
+
 fn show_quests_ui(
     ui: &mut egui::Ui,
     quests: &Vec<Quest>,
@@ -224,10 +1029,16 @@ fn format_goal(goal: &crate::quest::Goal, item_database: &ItemDatabase, player:
             let current = player.inventory.gold;
             format!("Collect {} Gold ({}/{})", goal.required_amount, current, goal.required_amount)
         }
-        crate::quest::Objective::ReachJobLevel(job_name) => {
+        crate::quest::Objective::ReachJobLevel(job_id) => {
             // Use u128::from to ensure type compatibility for comparison display
-            let current = player.get_job(job_name.clone()).map_or(0, |j| u128::from(j.level));
-            format!("Reach Level {} in {:?} ({}/{})", goal.required_amount, job_name, current, goal.required_amount)
+            let current = player.get_job(*job_id).map_or(0, |j| u128::from(j.level));
+            format!(
+                "Reach Level {} in {} ({}/{})",
+                goal.required_amount,
+                player.job_name(*job_id),
+                current,
+                goal.required_amount
+            )
         }
         crate::quest::Objective::ReachLevel() => {
              // Use u128::from to ensure type compatibility for comparison display
@@ -245,8 +1056,8 @@ fn is_goal_reached(goal: &crate::quest::Goal, player: &Player) -> bool {
         crate::quest::Objective::CollectGold() => {
             player.inventory.gold >= goal.required_amount
         }
-        crate::quest::Objective::ReachJobLevel(job_name) => {
-            player.get_job(job_name.clone()).map_or(false, |j| u128::from(j.level) >= goal.required_amount)
+        crate::quest::Objective::ReachJobLevel(job_id) => {
+            player.get_job(*job_id).map_or(false, |j| u128::from(j.level) >= goal.required_amount)
         }
         crate::quest::Objective::ReachLevel() => {
             u128::from(player.level) >= goal.required_amount
@@ -263,8 +1074,8 @@ fn format_completed_goal(goal: &crate::quest::Goal, item_database: &ItemDatabase
         crate::quest::Objective::CollectGold() => {
             format!("Collect {} Gold", goal.required_amount)
         }
-        crate::quest::Objective::ReachJobLevel(job_name) => {
-            format!("Reach Level {} in {:?}", goal.required_amount, job_name)
+        crate::quest::Objective::ReachJobLevel(job_id) => {
+            format!("Reach Level {} in {}", goal.required_amount, player.job_name(*job_id))
         }
         crate::quest::Objective::ReachLevel() => {
             format!("Reach Player Level {}", goal.required_amount)
@@ -272,11 +1083,11 @@ fn format_completed_goal(goal: &crate::quest::Goal, item_database: &ItemDatabase
     }
 }
 // Helper function to format quest rewards
-fn format_reward(reward: &crate::quest::Reward, item_database: &ItemDatabase) -> String {
+fn format_reward(reward: &crate::quest::Reward, item_database: &ItemDatabase, player: &Player) -> String {
     let mut parts = Vec::new();
     // Use the tuple format as per the current quest.rs code
     if let Some(experience) = &reward.experience {
-        parts.push(format!("{} {:?} XP", experience.amount, experience.job));
+        parts.push(format!("{} {} XP", experience.amount, player.job_name(experience.job)));
     }
     if let Some(items) = &reward.items {
         for item in items {
@@ -384,7 +1195,7 @@ fn quest_ui_component(
             ui.add_space(4.0);
         }
 
-        ui.label(format!("Reward: {}", format_reward(&quest_data.reward, item_database)));
+        ui.label(format!("Reward: {}", format_reward(&quest_data.reward, item_database, player)));
          
         if is_completable {
             if is_goal_reached(&quest_data.goal, player) {
@@ -412,10 +1223,22 @@ fn show_jobs_ui(ui: &mut egui::Ui, player: &Player) {
     ui.separator();
     ui.label("Jobs:");
     for job in &player.jobs {
+        let next_level = match job.get_xp_needed_for_next_level() {
+            Some(needed) => format!("{} to next level", needed),
+            None => "max level".to_string(),
+        };
         ui.label(format!(
-            "{}: level: {} | xp: {}",
-            job.name, job.level, job.experience
+            "{} {}: level: {} | xp: {} ({})",
+            job.icon, job.name, job.level, job.experience, next_level
         ));
+        for perk in &job.perks {
+            let text = format!("    Level {} {}: {}", perk.level, perk.name, perk.effect);
+            if perk.level <= job.level {
+                ui.label(text);
+            } else {
+                ui.label(egui::RichText::new(text).weak());
+            }
+        }
     }
 }
 
@@ -430,23 +1253,57 @@ fn show_player_stats_ui(
     ui.label(format!("Mana: {}", player.mana));
     ui.label(format!("Attack Power: {}", player.attack_power));
     ui.label(format!("Defense: {}", player.defense));
-    ui.label(format!("Level: {}", player.level));
+    match player.get_xp_needed_for_next_level() {
+        Some(needed) => ui.label(format!("Level: {} ({} XP to next level)", player.level, needed)),
+        None => ui.label(format!("Level: {} (max level)", player.level)),
+    };
     ui.label(format!("Gold: {}", player.inventory.gold));
     // Display inventory with names
     ui.label("Inventory:");
     if player.inventory.items.is_empty() {
         ui.label("  (Empty)");
     } else {
-        for (id, item) in &player.inventory.items {
+        for item in player.inventory.list_items(item_database, "", None, InventorySort::Name, true) {
             let item_name = item_database
-                .get(id)
+                .get(&item.id)
                 .map_or("Unknown Item", |data| &data.name);
             ui.label(format!("  {}: {}", item_name, item.quantity));
         }
     }
     let current_activity = match &player.current_activity {
-        Some(act) => format!("{:?}", act.name),
+        Some(act) => act.name.clone(),
         None => "Nothing".to_string(),
     };
     ui.label(format!("Current Activity: {}", current_activity));
 }
+
+mod tests {
+    use super::*;
+    use crate::utils::load_item_database;
+
+    #[test]
+    fn test_item_icons_load() {
+        let ctx = egui::Context::default();
+        let item_database = load_item_database().unwrap();
+        let mut item_icons = HashMap::new();
+
+        for item_data in item_database.values().filter(|item_data| item_data.icon.is_some()) {
+            assert!(item_icon(&ctx, &mut item_icons, item_data).is_some(), "{}", item_data.name);
+        }
+        assert_eq!(item_icon(&ctx, &mut item_icons, &item_database[&1]).unwrap().size(), [16, 16]);
+    }
+
+    #[test]
+    fn test_missing_item_icon_is_not_tried_again() {
+        let ctx = egui::Context::default();
+        let mut item_icons = HashMap::new();
+        let item_data = ItemData {
+            icon: Some("src/database/icons/missing.png".to_string()),
+            ..ItemData::new(99, "Missing", "", ItemCategory::Resource)
+        };
+
+        assert!(item_icon(&ctx, &mut item_icons, &item_data).is_none());
+        assert!(item_icons.get(&99).is_some_and(|icon| icon.is_none()));
+        assert!(load_icon(&ctx, "src/database/items.json").is_err());
+    }
+}
@@ -1,8 +1,21 @@
+use crate::item::Item;
 use crate::item::ItemData;
 use std::collections::HashMap;
 use crate::constants::ITEM_DATABASE_PATH;
 use crate::constants::QUEST_DATABASE_PATH;
+use crate::constants::ACTIVITY_DATABASE_PATH;
+use crate::constants::RECIPE_DATABASE_PATH;
+use crate::constants::JOB_DATABASE_PATH;
+use crate::constants::XP_CURVE_DATABASE_PATH;
+use crate::constants::OFFLINE_CONFIG_PATH;
+use crate::constants::VENDOR_DATABASE_PATH;
 use crate::quest::QuestData;
+use crate::activity::ActivityData;
+use crate::crafting::RecipeData;
+use crate::job::JobData;
+use crate::xp_curve::XpCurve;
+use crate::offline::OfflineConfig;
+use crate::shop::VendorData;
 use std::fs::File;
 use std::io::BufReader;
 
@@ -38,6 +51,181 @@ pub fn load_quest_database() -> Result<QuestDatabase, Box<dyn std::error::Error>
     Ok(quest_db)
 }
 
+pub type ActivityDatabase = HashMap<u128, ActivityData>;
+pub fn load_activity_database() -> Result<ActivityDatabase, Box<dyn std::error::Error>> {
+    let file = File::open(ACTIVITY_DATABASE_PATH)?;
+    let reader = BufReader::new(file);
+
+    let activities_vec: Vec<ActivityData> = serde_json::from_reader(reader)?;
+
+    let mut activity_db = ActivityDatabase::new();
+    for activity in activities_vec {
+        activity_db.insert(activity.id, activity);
+    }
+
+    Ok(activity_db)
+}
+
+pub type RecipeDatabase = HashMap<u128, RecipeData>;
+pub fn load_recipe_database() -> Result<RecipeDatabase, Box<dyn std::error::Error>> {
+    let file = File::open(RECIPE_DATABASE_PATH)?;
+    let reader = BufReader::new(file);
+
+    let recipes_vec: Vec<RecipeData> = serde_json::from_reader(reader)?;
+
+    let mut recipe_db = RecipeDatabase::new();
+    for recipe in recipes_vec {
+        recipe_db.insert(recipe.id, recipe);
+    }
+
+    Ok(recipe_db)
+}
+
+pub type JobDatabase = HashMap<u128, JobData>;
+
+pub fn load_job_database() -> Result<JobDatabase, Box<dyn std::error::Error>> {
+    let file = File::open(JOB_DATABASE_PATH)?;
+    let reader = BufReader::new(file);
+
+    let jobs_vec: Vec<JobData> = serde_json::from_reader(reader)?;
+
+    let mut job_db = JobDatabase::new();
+    for job in jobs_vec {
+        job_db.insert(job.id, job);
+    }
+
+    Ok(job_db)
+}
+
+pub type XpCurveDatabase = HashMap<u128, XpCurve>;
+
+pub fn load_xp_curve_database() -> Result<XpCurveDatabase, Box<dyn std::error::Error>> {
+    let file = File::open(XP_CURVE_DATABASE_PATH)?;
+    let reader = BufReader::new(file);
+
+    let curves_vec: Vec<XpCurve> = serde_json::from_reader(reader)?;
+
+    let mut curve_db = XpCurveDatabase::new();
+    for curve in curves_vec {
+        curve_db.insert(curve.id, curve);
+    }
+
+    Ok(curve_db)
+}
+
+pub type VendorDatabase = HashMap<u128, VendorData>;
+
+// The databases the game looks things up in while it runs, loaded once at startup
+pub struct Databases {
+    pub items: ItemDatabase,
+    pub quests: QuestDatabase,
+    pub activities: ActivityDatabase,
+    pub recipes: RecipeDatabase,
+    pub vendors: VendorDatabase,
+}
+
+pub fn load_vendor_database() -> Result<VendorDatabase, Box<dyn std::error::Error>> {
+    let file = File::open(VENDOR_DATABASE_PATH)?;
+    let reader = BufReader::new(file);
+
+    let vendors_vec: Vec<VendorData> = serde_json::from_reader(reader)?;
+
+    let mut vendor_db = VendorDatabase::new();
+    for vendor in vendors_vec {
+        vendor_db.insert(vendor.id, vendor);
+    }
+
+    Ok(vendor_db)
+}
+
+// Offline cap and efficiency curve
+pub fn load_offline_config() -> Result<OfflineConfig, Box<dyn std::error::Error>> {
+    let file = File::open(OFFLINE_CONFIG_PATH)?;
+    let reader = BufReader::new(file);
+
+    let offline_config: OfflineConfig = serde_json::from_reader(reader)?;
+
+    Ok(offline_config)
+}
+
+// Formats a number of seconds as the two largest units, e.g. "3d 4h" or "5m 12s"
+pub fn format_duration(seconds: u64) -> String {
+    let units = [("d", 86400), ("h", 3600), ("m", 60), ("s", 1)];
+    let parts: Vec<String> = units
+        .iter()
+        .scan(seconds, |remaining, (name, size)| {
+            let amount = *remaining / size;
+            *remaining %= size;
+            Some((amount, name))
+        })
+        .skip_while(|(amount, _)| *amount == 0)
+        .take(2)
+        .map(|(amount, name)| format!("{}{}", amount, name))
+        .collect();
+
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+// e.g. "3x Log, 1x Plank"
+pub fn format_items(items: &[Item], item_database: &ItemDatabase) -> String {
+    let parts: Vec<String> = items
+        .iter()
+        .map(|item| {
+            let item_name = item_database.get(&item.id).map_or("Unknown Item", |d| d.name.as_str());
+            format!("{}x {}", item.quantity, item_name)
+        })
+        .collect();
+    parts.join(", ")
+}
+
+// Parses a duration such as "90", "45s", "2h" or "1d 12h" into seconds
+pub fn parse_duration(text: &str) -> Result<u64, String> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Ok(seconds);
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let size = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(format!("invalid duration: {}", text)),
+        };
+        let amount: u64 = number.parse().map_err(|_| format!("invalid duration: {}", text))?;
+        seconds = amount
+            .checked_mul(size)
+            .and_then(|amount| seconds.checked_add(amount))
+            .ok_or_else(|| format!("duration too long: {}", text))?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("missing unit after {} in {}", number, text));
+    }
+    Ok(seconds)
+}
+
+pub fn current_timestamp() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 mod tests {
     use super::*;
 
@@ -54,4 +242,67 @@ mod tests {
         //just check that its not empty
         assert!(!quest_database.is_empty());
     }
+
+    #[test]
+    fn test_load_activity_database() {
+        let activity_database = load_activity_database().unwrap();
+        //just check that its not empty
+        assert!(!activity_database.is_empty());
+    }
+
+    #[test]
+    fn test_load_recipe_database() {
+        let recipe_database = load_recipe_database().unwrap();
+        //just check that its not empty
+        assert!(!recipe_database.is_empty());
+    }
+
+    #[test]
+    fn test_load_job_database() {
+        let job_database = load_job_database().unwrap();
+        //just check that its not empty
+        assert!(!job_database.is_empty());
+    }
+
+    #[test]
+    fn test_load_xp_curve_database() {
+        let xp_curve_database = load_xp_curve_database().unwrap();
+        let job_database = load_job_database().unwrap();
+        //every job needs a curve that reaches its max level
+        for job_data in job_database.values() {
+            let curve = &xp_curve_database[&job_data.xp_curve];
+            assert_eq!(curve.max_level(), curve.max_level);
+        }
+    }
+
+    #[test]
+    fn test_load_offline_config() {
+        let offline_config = load_offline_config().unwrap();
+        assert!(offline_config.max_seconds > 0);
+        assert!(offline_config
+            .efficiency
+            .windows(2)
+            .all(|tiers| tiers[0].until_seconds < tiers[1].until_seconds));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration("45s"), Ok(45));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+        assert_eq!(parse_duration("1d 12h"), Ok(129600));
+        assert_eq!(parse_duration("1h30m15s"), Ok(5415));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("2w").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(3600), "1h 0m");
+        assert_eq!(format_duration(3 * 86400 + 4 * 3600 + 59), "3d 4h");
+    }
 }
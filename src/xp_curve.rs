@@ -0,0 +1,141 @@
+// How much experience each level of a job costs. Curves are defined in xp_curves.json and jobs
+// refer to them by id, so a save only stores the id of the curve.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum XpFormula {
+    // table[i] is the experience to go from level i + 1 to level i + 2
+    Table(Vec<u128>),
+    // the experience to reach level L is base + factor * (L - 1)^exponent, rounded
+    Power { base: f64, factor: f64, exponent: f64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct XpCurve {
+    pub id: u128,
+    pub name: String,
+    pub max_level: u8,
+    pub formula: XpFormula,
+}
+
+// A job whose curve has not been looked up yet stays at level 1
+impl Default for XpCurve {
+    fn default() -> Self {
+        Self { id: 0, name: String::new(), max_level: 1, formula: XpFormula::Table(vec![]) }
+    }
+}
+
+impl XpCurve {
+    // The highest level that can be reached, a table can end before max_level
+    pub fn max_level(&self) -> u8 {
+        match &self.formula {
+            XpFormula::Table(table) => self.max_level.min(u8::try_from(table.len() + 1).unwrap_or(u8::MAX)),
+            XpFormula::Power { .. } => self.max_level,
+        }
+        .max(1)
+    }
+
+    // The experience to go from level to level + 1, None at the max level
+    pub fn experience_to_next(&self, level: u8) -> Option<u128> {
+        if level == 0 || level >= self.max_level() {
+            return None;
+        }
+        match &self.formula {
+            XpFormula::Table(table) => table.get(usize::from(level) - 1).copied(),
+            XpFormula::Power { base, factor, exponent } => {
+                Some((base + factor * f64::from(level).powf(*exponent)).round().max(0.0) as u128)
+            }
+        }
+    }
+
+    // The experience to go from level 1 to level
+    pub fn experience_to_reach(&self, level: u8) -> u128 {
+        (1..level).filter_map(|level| self.experience_to_next(level)).sum()
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use crate::utils::XpCurveDatabase;
+
+    pub const STANDARD: u128 = 1;
+    pub const PLAYER: u128 = 2;
+
+    // The first levels of the standard curve in xp_curves.json
+    pub fn standard_curve() -> XpCurve {
+        XpCurve {
+            id: STANDARD,
+            name: "Standard".to_string(),
+            max_level: 100,
+            formula: XpFormula::Table(vec![110, 170, 314, 576, 995, 1611, 2470, 3621, 5118, 7017]),
+        }
+    }
+
+    // Twice the standard curve, for the experience of every job added up
+    pub fn player_curve() -> XpCurve {
+        XpCurve {
+            id: PLAYER,
+            name: "Player".to_string(),
+            max_level: 100,
+            formula: XpFormula::Table(vec![220, 340, 628, 1152, 1990]),
+        }
+    }
+
+    pub fn xp_curve_database() -> XpCurveDatabase {
+        XpCurveDatabase::from([(STANDARD, standard_curve()), (PLAYER, player_curve())])
+    }
+
+    // The experience to go from level to level + 1 on the standard curve
+    pub fn level_up_experience(level: u8) -> u128 {
+        standard_curve().experience_to_next(level).unwrap()
+    }
+}
+
+mod tests {
+    use super::*;
+
+    fn power_curve() -> XpCurve {
+        XpCurve {
+            id: 2,
+            name: "Steep".to_string(),
+            max_level: 50,
+            formula: XpFormula::Power { base: 100.0, factor: 10.0, exponent: 2.8 },
+        }
+    }
+
+    #[test]
+    fn test_table_curve() {
+        let curve = XpCurve {
+            id: 1,
+            name: "Short".to_string(),
+            max_level: 10,
+            formula: XpFormula::Table(vec![100, 200, 300]),
+        };
+
+        assert_eq!(curve.max_level(), 4);
+        assert_eq!(curve.experience_to_next(1), Some(100));
+        assert_eq!(curve.experience_to_next(3), Some(300));
+        assert_eq!(curve.experience_to_next(4), None);
+        assert_eq!(curve.experience_to_reach(4), 600);
+    }
+
+    #[test]
+    fn test_power_curve() {
+        let curve = power_curve();
+
+        assert_eq!(curve.max_level(), 50);
+        assert_eq!(curve.experience_to_next(1), Some(110));
+        assert_eq!(curve.experience_to_next(2), Some(170));
+        assert!(curve.experience_to_next(49).is_some());
+        assert_eq!(curve.experience_to_next(50), None);
+        assert_eq!(curve.experience_to_reach(3), 280);
+    }
+
+    #[test]
+    fn test_default_curve_has_no_levels() {
+        assert_eq!(XpCurve::default().max_level(), 1);
+        assert_eq!(XpCurve::default().experience_to_next(1), None);
+    }
+}